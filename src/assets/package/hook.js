@@ -0,0 +1,10 @@
+#!/usr/bin/env node
+// The {{hook}} hook of {{name}}.
+//
+// git passes its hook arguments to this script, and the package directory is
+// available as $TACKLE_PACKAGE_DIR. Exit with a non-zero status to fail the hook.
+const args = process.argv.slice(2);
+const packageDir = process.env.TACKLE_PACKAGE_DIR || ".";
+
+console.log(`Running {{name}} {{hook}} hook from ${packageDir} with arguments: ${args.join(" ")}`);
+process.exit(0);
@@ -0,0 +1,18 @@
+#!/usr/bin/env python3
+"""The {{hook}} hook of {{name}}.
+
+git passes its hook arguments to this script, and the package directory is
+available as $TACKLE_PACKAGE_DIR. Exit with a non-zero status to fail the hook.
+"""
+import os
+import sys
+
+
+def main(args):
+    package_dir = os.environ.get("TACKLE_PACKAGE_DIR", ".")
+    print(f"Running {{name}} {{hook}} hook from {package_dir} with arguments: {args}")
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main(sys.argv[1:]))
@@ -0,0 +1,8 @@
+#!/bin/sh
+# The {{hook}} hook of {{name}}.
+#
+# git passes its hook arguments to this script, and the package directory is
+# available as $TACKLE_PACKAGE_DIR. Exit with a non-zero status to fail the hook.
+set -eu
+
+echo "Running {{name}} {{hook}} hook with arguments: $*"
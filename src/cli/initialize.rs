@@ -2,7 +2,8 @@ use log::info;
 
 use crate::{
     errors::TackleError,
-    project::{check_tackle_directory_exists, create_tackle_directory},
+    package::link::link_hooks,
+    project::{check_tackle_directory_exists, create_tackle_directory, read_manifest},
 };
 
 /// Initialize a new project.
@@ -11,7 +12,7 @@ pub fn initialize() -> Result<(), TackleError> {
     let repo =
         git2::Repository::discover(&cwd).map_err(|_err| TackleError::RepositoryDiscoveryFailed)?;
     // check repoistory work directory exists
-    if let None = repo.workdir() {
+    if repo.workdir().is_none() {
         return Err(TackleError::RepositoryDiscoveryFailed);
     }
     // check if tackle directory exists
@@ -22,5 +23,6 @@ pub fn initialize() -> Result<(), TackleError> {
     // create tackle directory
     info!("Initializing a new project with Tackle...");
     create_tackle_directory(workdir)?;
+    link_hooks(workdir, &read_manifest(workdir)?)?;
     Ok(())
 }
@@ -1,48 +1,331 @@
+use std::{collections::BTreeMap, path::Path};
+
 use log::info;
 
 use crate::{
     errors::TackleError,
-    package::fetch_package,
-    project::{get_project_root, is_initialized, read_manifest, TackleManifestHook},
+    lockfile::{read_lockfile, write_lockfile, LockedPackage, TackleLockfile},
+    package::{
+        delete_package, fetch_package,
+        index::{is_package_name, resolve_package_name},
+        link::link_hooks,
+        resolve::{resolve_dependencies, FetchingRegistry},
+        FetchedPackage, Package,
+    },
+    project::{
+        get_project_root, is_initialized, read_manifest, write_manifest, TackleManifest,
+        TackleManifestHook,
+    },
+    util::{is_local_package, split_package_specifier, PackageRevision},
 };
 
-/// Install a git hook from the target repository.
-pub fn install(url: String) -> Result<(), TackleError> {
+/// The URL and commit of a package checkout no longer locked.
+pub(crate) type StaleCheckout = (String, String);
+
+/// Get the revision the manifest requests for each of its packages.
+pub(crate) fn manifest_requirements(
+    manifest: &TackleManifest,
+) -> BTreeMap<String, Option<PackageRevision>> {
+    manifest
+        .hooks
+        .packages()
+        .map(|entry| (entry.url.clone(), PackageRevision::parse(&entry.version)))
+        .collect()
+}
+
+/// Resolve the dependency graph of the required packages, and fetch every
+/// package in it. Packages listed in `unlocked` are resolved afresh, others
+/// keep their locked commit while it still satisfies the graph. The commits of
+/// the fetched packages are recorded in the manifest and the lockfile.
+///
+/// The packages whose checkouts are no longer locked are returned along with
+/// the fetched packages. They must only be deleted with `delete_stale_packages`
+/// once the manifest and the lockfile are written, so that a failure never
+/// leaves the project pinned to a deleted checkout.
+pub(crate) fn resolve_packages(
+    workdir: &Path,
+    manifest: &mut TackleManifest,
+    lockfile: &mut TackleLockfile,
+    requirements: &BTreeMap<String, Option<PackageRevision>>,
+    unlocked: &[String],
+    offline: bool,
+) -> Result<(BTreeMap<String, FetchedPackage>, Vec<StaleCheckout>), TackleError> {
+    let previous = lockfile.packages.clone();
+    let locked = lockfile.packages.iter().filter(|locked| !unlocked.contains(&locked.url)).cloned();
+    let mut registry = FetchingRegistry::new(workdir, offline, locked);
+    let resolved = resolve_dependencies(requirements, &mut registry)?;
+    let fetched = registry.fetch(&resolved)?;
+
+    for (url, package) in &fetched {
+        for entry in manifest.hooks.packages_mut().filter(|entry| &entry.url == url) {
+            entry.commit = package.commit.clone();
+            entry.integrity = package.integrity.clone();
+        }
+        // packages inside the project are not locked
+        if !is_local_package(url) {
+            lockfile.insert(LockedPackage::new(url, package)?);
+        }
+    }
+    lockfile.packages.retain(|locked| fetched.contains_key(&locked.url));
+
+    let stale = previous
+        .into_iter()
+        .filter(|old| lockfile.get(&old.url).is_none_or(|locked| locked.commit != old.commit))
+        .map(|old| (old.url, old.commit))
+        .collect();
+    Ok((fetched, stale))
+}
+
+/// Delete the checkouts of the packages `resolve_packages` found stale.
+pub(crate) fn delete_stale_packages(
+    workdir: &Path,
+    stale: &[StaleCheckout],
+    lockfile: &TackleLockfile,
+) -> Result<(), TackleError> {
+    for (url, commit) in stale {
+        delete_package(workdir, url, commit, lockfile)?;
+    }
+    Ok(())
+}
+
+/// Check that the manifest entries of a package only select hooks it defines,
+/// and set valid values for the options it declares.
+fn check_entries(
+    manifest: &TackleManifest,
+    url: &str,
+    package: &Package,
+) -> Result<(), TackleError> {
+    for (hook, entries) in &manifest.hooks.0 {
+        for entry in
+            entries.iter().filter_map(|entry| entry.package()).filter(|entry| entry.url == url)
+        {
+            let definitions = package.hooks.0.get(hook).map_or(&[][..], |hooks| hooks.as_slice());
+            entry.check_selection(*hook, definitions)?;
+            entry.option_values(&package.options)?;
+        }
+    }
+    Ok(())
+}
+
+/// Install a git hook from the target repository, along with its dependencies.
+/// When `offline` is set, the package is resolved only from the cache and
+/// vendored copies.
+pub fn install(specifier: String, offline: bool) -> Result<(), TackleError> {
     if !is_initialized() {
         return Err(TackleError::NotInitialized);
     }
-    let workdir = get_project_root()?;
-    let _manifest = read_manifest(&workdir)?;
-    // install package
-    info!("Installing '{}'...", url);
-    let _package = fetch_package(&url)?;
-    // create the manifest hook entry
-    let _manifest_hook = TackleManifestHook {
+    install_package(&get_project_root()?, &specifier, offline)
+}
+
+/// Install a git hook into the project at `workdir`.
+pub(crate) fn install_package(
+    workdir: &Path,
+    specifier: &str,
+    offline: bool,
+) -> Result<(), TackleError> {
+    let mut manifest = read_manifest(workdir)?;
+    let mut lockfile = read_lockfile(workdir)?;
+    let (url, revision) = split_package_specifier(specifier);
+    // bare names are looked up in the package indexes
+    let url = match is_package_name(&url) {
+        true => resolve_package_name(&manifest.settings.indexes, &url, offline)?,
+        false => url,
+    };
+    // keep the existing revision when updating without a specifier
+    let existing = manifest.hooks.find_package(&url);
+    let revision = match (revision, existing) {
+        (None, Some(entry)) => PackageRevision::parse(&entry.version),
+        (revision, _) => revision,
+    };
+    match existing {
+        Some(_) => info!("Updating '{}'...", url),
+        None => info!("Installing '{}'...", url),
+    }
+    let mut requirements = manifest_requirements(&manifest);
+    requirements.insert(url.clone(), revision.clone());
+    let (fetched, stale) = resolve_packages(
+        workdir,
+        &mut manifest,
+        &mut lockfile,
+        &requirements,
+        std::slice::from_ref(&url),
+        offline,
+    )?;
+    let fetched = &fetched[&url];
+    let hooks: Vec<_> = fetched
+        .package
+        .hooks
+        .0
+        .iter()
+        .filter(|(_, definitions)| !definitions.is_empty())
+        .map(|(hook, _)| *hook)
+        .collect();
+    if hooks.is_empty() {
+        return Err(TackleError::PackageHasNoHooks(url));
+    }
+    let manifest_hook = TackleManifestHook {
         url: url.clone(),
-        integrity: "".to_owned(),
-        version: "1".to_owned(),
+        version: revision.as_ref().map_or_else(|| "*".to_owned(), |revision| revision.to_string()),
+        commit: fetched.commit.clone(),
+        integrity: fetched.integrity.clone(),
+        only: Vec::new(),
+        skip: Vec::new(),
+        overrides: BTreeMap::new(),
+        options: BTreeMap::new(),
     };
-    // // create the new manifest file
-    // let append_hook = |mut hooks: Vec<TackleManifestHook>| {
-    //     hooks.push(manifest_hook.clone());
-    //     hooks
-    // };
-    // let new_manifest = match hook {
-    //     Hook::PreCommit => TackleManifest {
-    //         hooks: TackleManifestHooks {
-    //             precommit: Some(
-    //                 manifest
-    //                     .hooks
-    //                     .precommit
-    //                     .map_or(vec![manifest_hook.clone()], append_hook),
-    //             ),
-    //             ..manifest.hooks
-    //         },
-    //         ..manifest
-    //     },
-    // };
-    // // write the new manifest
-    // write_manifest(&workdir, &new_manifest)?;
+    manifest.hooks.insert_package(&hooks, manifest_hook);
+    check_entries(&manifest, &url, &fetched.package)?;
+    // write the new manifest and lockfile
+    write_manifest(workdir, &manifest)?;
+    write_lockfile(workdir, &lockfile)?;
+    delete_stale_packages(workdir, &stale, &lockfile)?;
+    link_hooks(workdir, &manifest)?;
+    info!(
+        "Installed '{}' ({}) for {}",
+        url,
+        fetched.label(),
+        hooks.iter().map(|hook| hook.git_name()).collect::<Vec<_>>().join(", ")
+    );
 
     Ok(())
 }
+
+/// Install every package in the manifest and their dependencies at the commit
+/// pinned by the lockfile.
+///
+/// If the manifest and the lockfile disagree, the dependency graph is resolved
+/// again and the lockfile updated, unless `frozen` is set, in which case any
+/// disagreement is an error. When `offline` is set, packages are resolved only from
+/// the cache and vendored copies.
+pub fn install_all(frozen: bool, offline: bool) -> Result<(), TackleError> {
+    if !is_initialized() {
+        return Err(TackleError::NotInitialized);
+    }
+    let workdir = get_project_root()?;
+    let mut manifest = read_manifest(&workdir)?;
+    let mut lockfile = read_lockfile(&workdir)?;
+    let changed = lockfile.diff(&manifest);
+    if frozen && !changed.is_empty() {
+        return Err(TackleError::LockfileOutOfDate(changed.join(", ")));
+    }
+
+    if changed.is_empty() {
+        // the lockfile is up to date, so install exactly the locked packages
+        for locked in &lockfile.packages {
+            info!("Installing '{}'...", locked.url);
+            let commit = PackageRevision::Commit(locked.commit.clone());
+            let fetched = fetch_package(&workdir, &locked.url, Some(&commit), offline)?;
+            if fetched.integrity != locked.integrity {
+                return Err(TackleError::IntegrityMismatch {
+                    package: locked.url.clone(),
+                    expected: locked.integrity.clone(),
+                    actual: fetched.integrity,
+                });
+            }
+            check_entries(&manifest, &locked.url, &fetched.package)?;
+        }
+        for url in manifest.hooks.package_urls().iter().filter(|url| is_local_package(url)) {
+            check_entries(&manifest, url, &fetch_package(&workdir, url, None, offline)?.package)?;
+        }
+    } else {
+        info!("Resolving {}...", changed.join(", "));
+        let requirements = manifest_requirements(&manifest);
+        let (fetched, stale) = resolve_packages(
+            &workdir,
+            &mut manifest,
+            &mut lockfile,
+            &requirements,
+            &changed,
+            offline,
+        )?;
+        for (url, fetched) in &fetched {
+            check_entries(&manifest, url, &fetched.package)?;
+        }
+        write_manifest(&workdir, &manifest)?;
+        write_lockfile(&workdir, &lockfile)?;
+        delete_stale_packages(&workdir, &stale, &lockfile)?;
+    }
+
+    link_hooks(&workdir, &manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::install_package;
+    use crate::{
+        errors::TackleError,
+        hooks::Hook,
+        package::resolve_package_directory,
+        project::{read_manifest, write_manifest},
+        testing::{commit_files, init_origin, init_project, tag_commit, use_test_cache},
+    };
+
+    #[test]
+    fn test_failed_upgrade_keeps_checkout() {
+        use_test_cache();
+        let project = init_project();
+        let workdir = project.path();
+        let (_origin, repository, url) = init_origin(".");
+        let hooks = r#"[{ id = "fmt", command = ["true"] }, { id = "lint", command = ["true"] }]"#;
+        let old = commit_files(
+            &repository,
+            &[("package.toml", &format!("[hooks]\npre-commit = {}\n", hooks))],
+        );
+        tag_commit(&repository, "v1.0.0", &old);
+        install_package(workdir, &format!("{}@^1", url), false).unwrap();
+
+        // select a hook the next version no longer defines
+        let mut manifest = read_manifest(workdir).unwrap();
+        manifest.hooks.packages_mut().for_each(|entry| entry.only = vec!["lint".to_owned()]);
+        write_manifest(workdir, &manifest).unwrap();
+        let hooks = r#"[{ id = "fmt", command = ["true"] }]"#;
+        let new = commit_files(
+            &repository,
+            &[("package.toml", &format!("[hooks]\npre-commit = {}\n", hooks))],
+        );
+        tag_commit(&repository, "v1.1.0", &new);
+
+        let err = install_package(workdir, &url, false).err().unwrap();
+        assert!(matches!(
+            err,
+            TackleError::UnknownHookId { hook: Hook::PreCommit, id, .. } if id == "lint"
+        ));
+        // the project is still pinned to the old checkout, which still exists
+        let manifest = read_manifest(workdir).unwrap();
+        assert_eq!(manifest.hooks.find_package(&url).unwrap().commit, old);
+        assert!(resolve_package_directory(workdir, &url, &old).is_ok());
+    }
+
+    #[test]
+    fn test_check_entry_options() {
+        use_test_cache();
+        let project = init_project();
+        let workdir = project.path();
+        let (_origin, repository, url) = init_origin(".");
+        let package = r#"[options.max_line]
+type = "int"
+default = 100
+
+[hooks]
+pre-commit = [{ command = ["lint", "--max-line={{max_line}}"] }]
+"#;
+        commit_files(&repository, &[("package.toml", package)]);
+        install_package(workdir, &url, false).unwrap();
+
+        let set_option = |value: toml::Value| {
+            let mut manifest = read_manifest(workdir).unwrap();
+            let entry = manifest.hooks.packages_mut().next().unwrap();
+            entry.options.insert("max_line".to_owned(), value);
+            write_manifest(workdir, &manifest).unwrap();
+        };
+        set_option("wide".into());
+        let err = install_package(workdir, &url, false).err().unwrap();
+        assert!(matches!(err, TackleError::InvalidOption { option, .. } if option == "max_line"));
+
+        // the options of the entry are kept when the package is updated
+        set_option(120.into());
+        install_package(workdir, &url, false).unwrap();
+        let manifest = read_manifest(workdir).unwrap();
+        assert_eq!(manifest.hooks.find_package(&url).unwrap().options["max_line"], 120.into());
+    }
+}
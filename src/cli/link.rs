@@ -0,0 +1,27 @@
+use crate::{
+    errors::TackleError,
+    hooks::Hook,
+    package::link::{link_hooks, link_state, resolve_hooks_directory, used_hooks},
+    project::{get_project_root, is_initialized, read_manifest},
+};
+
+/// Link the hooks used by the project into the git hooks directory.
+pub fn link(status: bool) -> Result<(), TackleError> {
+    if !is_initialized() {
+        return Err(TackleError::NotInitialized);
+    }
+    let workdir = get_project_root()?;
+    let manifest = read_manifest(&workdir)?;
+    if !status {
+        return link_hooks(&workdir, &manifest);
+    }
+
+    let hooks_dir = resolve_hooks_directory(&workdir)?;
+    let used = used_hooks(&workdir, &manifest);
+    println!("Hooks directory: {}", hooks_dir.display());
+    for hook in Hook::ALL {
+        let state = link_state(&hooks_dir, hook, used.contains(&hook));
+        println!("\t{:<24}{:?}", hook.git_name(), state);
+    }
+    Ok(())
+}
@@ -1,9 +1,64 @@
 use crate::{
     errors::TackleError,
-    project::{get_project_root, is_initialized, read_manifest},
+    hooks::{
+        reference::{Namespaces, ID_SEPARATOR},
+        PROJECT_NAMESPACE,
+    },
+    package::{hook_ids, resolve_package_directory, HookCondition, Package},
+    project::{
+        get_project_root, is_initialized, read_manifest, EffectiveHook, TackleManifestEntry,
+    },
 };
 
-pub fn list() -> Result<(), TackleError> {
+/// Describe a condition as its non-empty fields.
+fn describe_condition(condition: &HookCondition) -> String {
+    let fields = [
+        ("successful", &condition.successful),
+        ("failed", &condition.failed),
+        ("skipped", &condition.skipped),
+        ("exists", &condition.exists),
+        ("branch", &condition.branch),
+    ];
+    let fields: Vec<_> = fields
+        .iter()
+        .filter(|(_, values)| !values.is_empty())
+        .map(|(name, values)| format!("{} = {}", name, values.join(", ")))
+        .collect();
+    match fields.is_empty() {
+        true => "always".to_owned(),
+        false => fields.join("; "),
+    }
+}
+
+/// Print the effective definition of a hook, marking the overridden fields.
+fn print_definition(hook: &EffectiveHook) {
+    let definition = &hook.definition;
+    let field = |name: &str, value: String| {
+        let marker = if hook.overridden.contains(&name) { " (overridden)" } else { "" };
+        let value = if value.is_empty() { "none".to_owned() } else { value };
+        println!("\t\t\t{:<16}{}{}", name, value, marker);
+    };
+    field("command", definition.command.join(" "));
+    if hook.overridden.contains(&"args") {
+        println!("\t\t\t{:<16}(extra arguments appended by the project)", "args");
+    }
+    field("dependencies", definition.dependencies.join(", "));
+    field("blocking", definition.blocking.to_string());
+    field("serial", definition.serial.to_string());
+    // hooks run if any of their conditions match
+    let conditions = match definition.conditions.is_empty() {
+        true => "always".to_owned(),
+        false => {
+            definition.conditions.iter().map(describe_condition).collect::<Vec<_>>().join(" | ")
+        }
+    };
+    field("conditions", conditions);
+}
+
+/// Print the packages installed for every git hook, and which of their hooks
+/// are active. When `verbose` is set, the effective definition of every hook
+/// is printed too.
+pub fn list(verbose: bool) -> Result<(), TackleError> {
     if !is_initialized() {
         return Err(TackleError::NotInitialized);
     }
@@ -11,19 +66,80 @@ pub fn list() -> Result<(), TackleError> {
     let workdir = get_project_root()?;
     let manifest = read_manifest(&workdir)?;
 
-    println!("Pre-commit Hooks:");
-    for hook in &manifest.hooks.precommit {
-        println!("\t{}", hook.url);
-    }
-    if manifest.hooks.precommit.is_empty() {
-        println!("\tNo hooks installed.");
-    }
-    println!("Post-commit Hooks:");
-    for hook in &manifest.hooks.postcommit {
-        println!("\t{}", hook.url);
+    let used = manifest.hooks.used_hooks();
+    for hook in &used {
+        println!("{} hooks:", hook);
+        // inline hooks form a single package
+        let inline: Vec<_> = manifest
+            .hooks
+            .get(*hook)
+            .iter()
+            .filter_map(|entry| match entry {
+                TackleManifestEntry::Inline(definition) => Some(definition.clone()),
+                TackleManifestEntry::Package(_) => None,
+            })
+            .collect();
+        let mut inline_ids = hook_ids(&inline).into_iter();
+        let mut namespaces = Namespaces::default();
+        for entry in manifest.hooks.get(*hook) {
+            let entry = match entry {
+                TackleManifestEntry::Package(entry) => entry,
+                TackleManifestEntry::Inline(definition) => {
+                    let id = inline_ids.next().unwrap_or_default();
+                    println!("\t{}{}{} (inline)", PROJECT_NAMESPACE, ID_SEPARATOR, id);
+                    if verbose {
+                        let effective = EffectiveHook {
+                            definition: definition.clone(),
+                            active: true,
+                            overridden: Vec::new(),
+                        };
+                        print_definition(&effective);
+                    }
+                    continue;
+                }
+            };
+            println!("\t{}", entry.url);
+            let package = match resolve_package_directory(&workdir, &entry.url, &entry.commit) {
+                Ok(directory) => Package::from_path(directory)?,
+                Err(_) => {
+                    println!("\t\t(not installed, run `tackle install`)");
+                    continue;
+                }
+            };
+            let namespace = namespaces.assign(&package.namespace(&entry.url), Some(&entry.url));
+            let effective = entry
+                .option_values(&package.options)
+                .and_then(|_| entry.effective_hooks(*hook, package.hooks.into_hook(*hook)));
+            let effective = match effective {
+                Ok(effective) => effective,
+                Err(err) => {
+                    println!("\t\t(invalid configuration: {})", err);
+                    continue;
+                }
+            };
+            let definitions: Vec<_> =
+                effective.iter().map(|effective| effective.definition.clone()).collect();
+            for (effective, id) in effective.iter().zip(hook_ids(&definitions)) {
+                let mut notes = Vec::new();
+                if !effective.active {
+                    notes.push("inactive".to_owned());
+                }
+                if !effective.overridden.is_empty() {
+                    notes.push(format!("overridden: {}", effective.overridden.join(", ")));
+                }
+                let id = format!("{}{}{}", namespace, ID_SEPARATOR, id);
+                match notes.is_empty() {
+                    true => println!("\t\t{}", id),
+                    false => println!("\t\t{} ({})", id, notes.join("; ")),
+                }
+                if verbose {
+                    print_definition(effective);
+                }
+            }
+        }
     }
-    if manifest.hooks.precommit.is_empty() {
-        println!("\tNo hooks installed.");
+    if used.is_empty() {
+        println!("No hooks installed.");
     }
 
     Ok(())
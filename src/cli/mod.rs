@@ -2,18 +2,34 @@
 
 mod initialize;
 mod install;
+mod link;
 mod list;
+mod outdated;
+mod package;
+mod query;
+mod remove;
+mod run;
+mod search;
+mod update;
 
-use std::str::FromStr;
+use std::path::PathBuf;
 
 use clap::{AppSettings, Parser, Subcommand};
 
 pub use initialize::*;
 pub use install::*;
+pub use link::*;
 pub use list::*;
 use log::{error, LevelFilter};
+pub use outdated::*;
+pub use package::*;
+pub use query::*;
+pub use remove::*;
+pub use run::*;
+pub use search::*;
+pub use update::*;
 
-use crate::errors::TackleError;
+use crate::{hooks::Hook, package::scaffold::Template};
 
 /// Multi-platform, agnostic git hook manager.
 #[derive(Parser)]
@@ -26,49 +42,127 @@ struct Args {
     debug: bool,
 }
 
-enum Hook {
-    PreCommit,
-}
-
-impl FromStr for Hook {
-    type Err = TackleError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "precommit" => Ok(Hook::PreCommit),
-            _ => Err(TackleError::InvalidCommitHook),
-        }
-    }
-}
-
 #[derive(Subcommand)]
 enum Commands {
     /// Install a git hook from the target repository.
     #[clap(setting(AppSettings::ArgRequiredElseHelp))]
-    #[clap(alias = "install", alias = "i")]
     Add {
-        /// The URL of the hook to add.
+        /// The URL of the hook to add, or a package name to look up in the indexes.
         url: String,
+        /// Resolve the package only from the local cache and vendored copies.
+        #[clap(long, env = "TACKLE_OFFLINE")]
+        offline: bool,
+    },
+    /// Install every package in the manifest at the commits pinned by the lockfile.
+    /// If a URL is given, this behaves like `add`.
+    #[clap(alias = "i")]
+    Install {
+        /// The URL of the hook to add.
+        url: Option<String>,
+        /// Fail if the manifest and the lockfile disagree, without updating either.
+        #[clap(long, conflicts_with = "url")]
+        frozen: bool,
+        /// Resolve packages only from the local cache and vendored copies.
+        #[clap(long, env = "TACKLE_OFFLINE")]
+        offline: bool,
     },
     /// Remove a git hook from the target repository.
     /// This will remove the hook from the repository and remove the hook file from the hooks directory.
     #[clap(setting(AppSettings::ArgRequiredElseHelp))]
 	#[clap(alias = "rm", alias = "uninstall", alias = "ui")]
-    Remove { package: String },
+    Remove {
+        /// The package to remove.
+        package: String,
+        /// Only remove the package from the manifest, keeping its files.
+        #[clap(long)]
+        keep_files: bool,
+    },
+    /// Update installed packages to the newest version matching their requested revision.
+    #[clap(alias = "up")]
+    Update {
+        /// The package to update. Updates every package if omitted.
+        package: Option<String>,
+        /// Update only to commits already in the local cache.
+        #[clap(long, env = "TACKLE_OFFLINE")]
+        offline: bool,
+    },
+    /// List installed packages with newer versions available.
+    Outdated,
     /// List all installed hooks.
 	#[clap(alias = "ls", alias = "l")]
-	List,
-    /// Query the hook repository for a list of available hooks.
+    List {
+        /// Show the effective definition of every hook, and the fields the project overrides.
+        #[clap(long, short)]
+        verbose: bool,
+    },
+    /// Link Tackle into the git hooks directory.
+    Link {
+        /// Show the link state of every git hook instead of linking.
+        #[clap(long)]
+        status: bool,
+    },
+    /// Run the installed hooks for a git hook.
+    #[clap(setting(AppSettings::ArgRequiredElseHelp))]
+    Run {
+        /// The git hook to run.
+        hook: Hook,
+        /// The maximum number of hooks to run in parallel. Defaults to the
+        /// `jobs` setting of the project, or 1.
+        #[clap(short, long)]
+        jobs: Option<usize>,
+        /// Arguments passed to the hook by git, after `--`.
+        args: Vec<String>,
+    },
+    /// List the packages and hooks a repository provides.
     #[clap(setting(AppSettings::ArgRequiredElseHelp))]
     Query {
-        /// The name of the package to query.
+        /// The repository to query, optionally with a revision.
         package: String,
     },
+    /// Search the package indexes for packages.
+    #[clap(setting(AppSettings::ArgRequiredElseHelp))]
+    Search {
+        /// The term to match against package names, descriptions and tags.
+        term: String,
+        /// Search only the cached copies of the indexes.
+        #[clap(long, env = "TACKLE_OFFLINE")]
+        offline: bool,
+    },
+    /// Develop Tackle packages.
+    #[clap(subcommand, alias = "pkg")]
+    Package(PackageCommands),
     /// Initialize this project with Tackle.
     #[clap(alias = "init")]
     Initialize,
 }
 
+#[derive(Subcommand)]
+enum PackageCommands {
+    /// Create a new package with example hooks, scripts and test fixtures.
+    #[clap(setting(AppSettings::ArgRequiredElseHelp))]
+    New {
+        /// The directory to create the package in.
+        path: PathBuf,
+        /// The name of the package. Defaults to the name of the directory.
+        #[clap(long)]
+        name: Option<String>,
+        /// A git hook to generate an example hook for. May be repeated.
+        #[clap(long = "hook", short = 'H')]
+        hooks: Vec<Hook>,
+        /// The language of the generated scripts: shell, python or node.
+        #[clap(long, short, default_value = "shell")]
+        template: Template,
+        /// Initialize a git repository in the package directory.
+        #[clap(long)]
+        git: bool,
+    },
+    /// Check a package manifest for mistakes.
+    Lint {
+        /// The package directory. Defaults to the current directory.
+        path: Option<PathBuf>,
+    },
+}
+
 pub fn run_cli() {
     let args = Args::parse();
     // if debug mode is enabled, initialize a more verbose logger.
@@ -85,12 +179,49 @@ pub fn run_cli() {
     use Commands::*;
     let res = match args.command {
         Initialize => initialize(),
-        Add { url } => install(url),
-        List => list(),
-        _ => todo!(),
+        Add { url, offline } | Install { url: Some(url), offline, .. } => install(url, offline),
+        Install { url: None, frozen, offline } => install_all(frozen, offline),
+        Remove { package, keep_files } => remove(package, keep_files),
+        Update { package, offline } => update(package, offline),
+        Outdated => outdated(),
+        List { verbose } => list(verbose),
+        Link { status } => link(status),
+        Run { hook, jobs, args } => run(hook, jobs, args),
+        Query { package } => query(package),
+        Search { term, offline } => search(term, offline),
+        Package(PackageCommands::New { path, name, hooks, template, git }) => {
+            new(path, name, hooks, template, git)
+        }
+        Package(PackageCommands::Lint { path }) => lint(path),
     };
     // run the error handler on error
     if let Err(e) = res {
         error!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::{Args, Commands};
+    use crate::hooks::Hook;
+
+    #[test]
+    fn test_parse_run_args() {
+        let parse = |args: &[&str]| match Args::try_parse_from(args).unwrap().command {
+            Commands::Run { hook, jobs, args } => (hook, jobs, args),
+            _ => panic!("expected the run command"),
+        };
+        // everything after `--` is passed to the hooks, as the shims do
+        let (hook, jobs, args) = parse(&["tackle", "run", "pre-push", "--", "origin", "--jobs"]);
+        assert_eq!((hook, jobs), (Hook::PrePush, None));
+        assert_eq!(args, vec!["origin", "--jobs"]);
+        let (_, jobs, args) = parse(&["tackle", "run", "commit-msg", "-j", "2", "--", "-m"]);
+        assert_eq!((jobs, args), (Some(2), vec!["-m".to_owned()]));
+        // shims generated before `--` was added still work
+        let (_, _, args) = parse(&["tackle", "run", "commit-msg", ".git/COMMIT_EDITMSG"]);
+        assert_eq!(args, vec![".git/COMMIT_EDITMSG"]);
     }
 }
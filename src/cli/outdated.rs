@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use crate::{
+    errors::TackleError,
+    lockfile::read_lockfile,
+    package::{
+        download::{fetch_tags, resolve_revision},
+        open_package_repository,
+        resolve::latest_tag,
+    },
+    project::{get_project_root, is_initialized, read_manifest},
+    util::{is_local_package, PackageRevision},
+};
+
+/// Format a resolved revision for display, preferring the tag or branch name.
+fn format_revision(commit: &str, label: Option<String>) -> String {
+    label.unwrap_or_else(|| commit.chars().take(7).collect())
+}
+
+/// The versions of an installed package.
+#[derive(Debug, PartialEq)]
+struct OutdatedPackage {
+    url: String,
+    /// The version the lockfile pins.
+    current: String,
+    /// The newest version matching the requested revision.
+    compatible: String,
+    /// The newest version, regardless of the requested revision.
+    latest: String,
+}
+
+/// Print the current, latest compatible and latest versions of every installed package.
+pub fn outdated() -> Result<(), TackleError> {
+    if !is_initialized() {
+        return Err(TackleError::NotInitialized);
+    }
+    let packages = outdated_packages(&get_project_root()?)?;
+    println!("{:<40}{:<16}{:<16}{:<16}", "Package", "Current", "Compatible", "Latest");
+    for package in packages {
+        println!(
+            "{:<40}{:<16}{:<16}{:<16}",
+            package.url, package.current, package.compatible, package.latest
+        );
+    }
+    Ok(())
+}
+
+/// Resolve the versions of every package installed in the project at `workdir`.
+fn outdated_packages(workdir: &Path) -> Result<Vec<OutdatedPackage>, TackleError> {
+    let manifest = read_manifest(workdir)?;
+    let lockfile = read_lockfile(workdir)?;
+
+    let mut packages = Vec::new();
+    // packages inside the project have no versions
+    for url in manifest.hooks.package_urls().into_iter().filter(|url| !is_local_package(url)) {
+        let entry = manifest.hooks.find_package(&url).unwrap();
+        let current = format_revision(
+            &entry.commit,
+            lockfile.get(&url).and_then(|locked| locked.version.clone()),
+        );
+        let repository = open_package_repository(&url)?;
+        // packages pinned to a commit never have a newer compatible version
+        let revision = PackageRevision::parse(&entry.version);
+        let (commit, label) = match revision {
+            Some(PackageRevision::Commit(_)) => (entry.commit.clone(), None),
+            revision => resolve_revision(&repository, revision.as_ref(), false)?,
+        };
+        let compatible = format_revision(&commit, label);
+        // the latest version is the highest tag, or the default branch if there are none
+        let latest = match latest_tag(&fetch_tags(&repository)?) {
+            Some(tag) => tag,
+            None => {
+                let (commit, label) = resolve_revision(&repository, None, false)?;
+                format_revision(&commit, label)
+            }
+        };
+        packages.push(OutdatedPackage { url, current, compatible, latest });
+    }
+    Ok(packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{outdated_packages, OutdatedPackage};
+    use crate::{
+        cli::install_package,
+        testing::{commit_files, init_origin, init_project, tag_commit, use_test_cache},
+    };
+
+    #[test]
+    fn test_outdated_packages() {
+        use_test_cache();
+        let project = init_project();
+        let workdir = project.path();
+        let (_origin, repository, url) = init_origin(".");
+        let hooks = "[hooks]\npre-commit = [{ command = [\"true\"] }]\n";
+        let commit = commit_files(&repository, &[("package.toml", hooks)]);
+        tag_commit(&repository, "v1.0.0", &commit);
+        install_package(workdir, &format!("{}@^1", url), false).unwrap();
+        for version in ["v1.1.0", "v2.0.0"] {
+            let commit = commit_files(&repository, &[("CHANGELOG", version)]);
+            tag_commit(&repository, version, &commit);
+        }
+
+        let expected = OutdatedPackage {
+            url,
+            current: "v1.0.0".to_owned(),
+            compatible: "v1.1.0".to_owned(),
+            latest: "v2.0.0".to_owned(),
+        };
+        assert_eq!(outdated_packages(workdir).unwrap(), vec![expected]);
+    }
+}
@@ -0,0 +1,61 @@
+use std::path::PathBuf;
+
+use log::info;
+
+use crate::{
+    errors::TackleError,
+    hooks::Hook,
+    package::{
+        lint::{lint_package, Severity},
+        scaffold::{scaffold_package, Template},
+    },
+};
+
+/// Create a new package in `path`, named after the directory unless `name` is
+/// given. The package gets an example hook for every hook in `hooks`, or for
+/// `pre-commit` if none are given.
+pub fn new(
+    path: PathBuf,
+    name: Option<String>,
+    hooks: Vec<Hook>,
+    template: Template,
+    git: bool,
+) -> Result<(), TackleError> {
+    let name = match name {
+        Some(name) => name,
+        None => path
+            .canonicalize()
+            .unwrap_or_else(|_| path.clone())
+            .file_name()
+            .map_or_else(|| "package".to_owned(), |name| name.to_string_lossy().into_owned()),
+    };
+    let hooks = match hooks.is_empty() {
+        true => vec![Hook::PreCommit],
+        false => hooks,
+    };
+    scaffold_package(&path, &name, &hooks, template, git)?;
+    info!("Created package '{}', check it with `tackle package lint {}`", name, path.display());
+    Ok(())
+}
+
+/// Check the manifest of the package in `path`, printing every problem found.
+/// Fails if any of the problems is an error.
+pub fn lint(path: Option<PathBuf>) -> Result<(), TackleError> {
+    let path = path.unwrap_or_else(|| PathBuf::from("."));
+    let diagnostics = lint_package(&path)?;
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+    let errors = diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error);
+    match errors.count() {
+        0 if diagnostics.is_empty() => {
+            info!("No problems found in '{}'", path.join("package.toml").display());
+            Ok(())
+        }
+        0 => {
+            info!("Found {} warning(s)", diagnostics.len());
+            Ok(())
+        }
+        errors => Err(TackleError::PackageLintFailed(errors)),
+    }
+}
@@ -0,0 +1,61 @@
+use log::info;
+
+use crate::{
+    errors::TackleError,
+    package::{
+        download::resolve_revision, find_packages, hook_ids, open_package_repository,
+        source::GitSource,
+    },
+    util::split_package_specifier,
+};
+
+/// Print the packages and hooks provided by a repository.
+pub fn query(specifier: String) -> Result<(), TackleError> {
+    let (url, revision) = split_package_specifier(&specifier);
+    let source = GitSource::parse(&url)?;
+    info!("Querying '{}'...", url);
+    let repository = open_package_repository(&url)?;
+    let (commit, version) = resolve_revision(&repository, revision.as_ref(), false)?;
+    let packages = find_packages(&repository, &commit, &source.path)?;
+    if packages.is_empty() {
+        println!("No packages found in '{}'.", url);
+        return Ok(());
+    }
+
+    // packages are added by their path from the repository root
+    let root = match source.path.as_str() {
+        "." => url.as_str(),
+        path => url.trim_end_matches('/').strip_suffix(path).unwrap_or(&url).trim_end_matches('/'),
+    };
+    println!("Packages in '{}' at {}:", root, version.unwrap_or_else(|| commit[..7].to_owned()));
+    for (path, package) in packages {
+        let name = package.name.as_deref().unwrap_or_else(|| match path.rsplit_once('/') {
+            Some((_, name)) => name,
+            None => &path,
+        });
+        println!();
+        match &package.version {
+            Some(version) => println!("{} {}", name, version),
+            None => println!("{}", name),
+        }
+        if let Some(description) = &package.description {
+            println!("\t{}", description);
+        }
+        match path.as_str() {
+            "." => println!("\t{:<24}{}", "package", root),
+            path => println!("\t{:<24}{}/{}", "package", root, path),
+        }
+        for (hook, definitions) in &package.hooks.0 {
+            println!("\t{:<24}{}", hook.git_name(), hook_ids(definitions).join(", "));
+        }
+        for (name, option) in &package.options {
+            let default = option
+                .default
+                .as_ref()
+                .map_or_else(|| "required".to_owned(), |default| format!("default {}", default));
+            let option = format!("{}: {}, {}", name, option.kind, default);
+            println!("\t{:<24}{}", "option", option);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use std::path::Path;
+
+use log::info;
+
+use crate::{
+    errors::TackleError,
+    lockfile::{read_lockfile, write_lockfile},
+    package::{delete_package, link::link_hooks},
+    project::{get_project_root, is_initialized, read_manifest, write_manifest},
+};
+
+/// Remove a package from the project.
+pub fn remove(package: String, keep_files: bool) -> Result<(), TackleError> {
+    if !is_initialized() {
+        return Err(TackleError::NotInitialized);
+    }
+    remove_package(&get_project_root()?, package, keep_files)
+}
+
+/// Remove a package from the project at `workdir`. The package stays locked,
+/// and its checkout installed, while another package still depends on it.
+fn remove_package(workdir: &Path, package: String, keep_files: bool) -> Result<(), TackleError> {
+    let mut manifest = read_manifest(workdir)?;
+    if !manifest.hooks.contains_package(&package) {
+        return Err(TackleError::PackageNotInstalled(package));
+    }
+    manifest.hooks.remove_package(&package);
+    info!("Removing '{}'...", package);
+    let mut lockfile = read_lockfile(workdir)?;
+    // drop the package and the dependencies no other package needs
+    let pruned = lockfile.prune(&manifest);
+    for orphan in pruned.iter().filter(|orphan| orphan.url != package) {
+        info!("Removing unused dependency '{}'...", orphan.url);
+    }
+    write_manifest(workdir, &manifest)?;
+    write_lockfile(workdir, &lockfile)?;
+    if keep_files {
+        return Ok(());
+    }
+    for orphan in &pruned {
+        delete_package(workdir, &orphan.url, &orphan.commit, &lockfile)?;
+    }
+    // unlink the hooks that no longer need Tackle
+    link_hooks(workdir, &manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::remove_package;
+    use crate::{
+        cli::install_package,
+        lockfile::read_lockfile,
+        package::resolve_package_directory,
+        project::read_manifest,
+        testing::{commit_files, init_origin, init_project, tag_commit, use_test_cache},
+    };
+
+    #[test]
+    fn test_remove_dependency() {
+        use_test_cache();
+        let project = init_project();
+        let workdir = project.path();
+        let hooks = r#"[hooks]
+pre-commit = [{ id = "check", command = ["true"] }]
+"#;
+        let (_node_origin, node_repository, node) = init_origin(".");
+        let commit = commit_files(&node_repository, &[("package.toml", hooks)]);
+        tag_commit(&node_repository, "v1.0.0", &commit);
+        let (_app_origin, app_repository, app) = init_origin(".");
+        let dependencies = format!("[dependencies]\n\"{}\" = \"^1\"\n\n{}", node, hooks);
+        commit_files(&app_repository, &[("package.toml", &dependencies)]);
+        install_package(workdir, &node, false).unwrap();
+        install_package(workdir, &app, false).unwrap();
+
+        // the package is still needed by the other package
+        remove_package(workdir, node.clone(), false).unwrap();
+        assert!(!read_manifest(workdir).unwrap().hooks.contains_package(&node));
+        assert!(read_lockfile(workdir).unwrap().get(&node).is_some());
+        assert!(resolve_package_directory(workdir, &node, &commit).is_ok());
+
+        // removing the other package drops the dependency as well
+        remove_package(workdir, app, false).unwrap();
+        assert!(read_lockfile(workdir).unwrap().packages.is_empty());
+        assert!(resolve_package_directory(workdir, &node, &commit).is_err());
+    }
+}
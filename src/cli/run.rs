@@ -0,0 +1,75 @@
+use std::io::{self, Read};
+
+use log::{debug, warn};
+
+use crate::{
+    errors::TackleError,
+    hooks::{condition::current_branch, Hook, HookRunner, HookSource, PROJECT_NAMESPACE},
+    package::{
+        integrity::verify_integrity, options::OptionValues, resolve_package_directory, Package,
+    },
+    project::{
+        get_project_root, is_initialized, read_manifest, EffectiveHook, TackleManifestEntry,
+    },
+    util::is_local_package,
+};
+
+/// Run the installed hooks for the target git hook, with up to `jobs` hooks
+/// running in parallel.
+pub fn run(hook: Hook, jobs: Option<usize>, args: Vec<String>) -> Result<(), TackleError> {
+    if !is_initialized() {
+        return Err(TackleError::NotInitialized);
+    }
+    let workdir = get_project_root()?;
+    let manifest = read_manifest(&workdir)?;
+    // build the pipeline from every installed package and inline hook
+    let mut runner = HookRunner::new(current_branch(&workdir));
+    for entry in &manifest.hooks.run_entries(hook) {
+        let entry = match entry {
+            TackleManifestEntry::Package(entry) => entry,
+            // inline hooks run from the project root
+            TackleManifestEntry::Inline(definition) => {
+                let source = HookSource {
+                    namespace: PROJECT_NAMESPACE,
+                    url: None,
+                    directory: Some(workdir.clone()),
+                };
+                let definition = definition.clone();
+                let hook = EffectiveHook { definition, active: true, overridden: Vec::new() };
+                runner.add_hooks(source, &OptionValues::new(), vec![hook]);
+                continue;
+            }
+        };
+        debug!("Loading package '{}'...", entry.url);
+        let directory = resolve_package_directory(&workdir, &entry.url, &entry.commit)?;
+        // packages inside the project change as they are developed
+        if is_local_package(&entry.url) {
+            debug!("Package '{}' is inside the project, skipping verification", entry.url);
+        } else if entry.integrity.is_empty() {
+            warn!("Package '{}' has no integrity hash, skipping verification", entry.url);
+        } else {
+            verify_integrity(&directory, &entry.url, &entry.integrity)?;
+        }
+        let package = Package::from_path(&directory)?;
+        let options = entry.option_values(&package.options)?;
+        let namespace = package.namespace(&entry.url);
+        let hooks = entry.effective_hooks(hook, package.hooks.into_hook(hook))?;
+        let source =
+            HookSource { namespace: &namespace, url: Some(&entry.url), directory: Some(directory) };
+        runner.add_hooks(source, &options, hooks);
+    }
+    // buffer the input from git so that every hook can read it
+    let stdin = match hook.reads_stdin() {
+        true => {
+            let mut input = Vec::new();
+            if let Err(err) = io::stdin().read_to_end(&mut input) {
+                warn!("Failed to read hook input: {}", err);
+            }
+            Some(input)
+        }
+        false => None,
+    };
+    let jobs = jobs.or(manifest.settings.jobs).unwrap_or(1);
+    debug!("Running up to {} hooks in parallel", jobs);
+    runner.run(&args, stdin.as_deref(), jobs)
+}
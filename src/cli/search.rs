@@ -0,0 +1,46 @@
+use log::info;
+
+use crate::{
+    errors::TackleError,
+    hooks::Hook,
+    package::index::read_index,
+    project::{get_project_root, is_initialized, read_manifest},
+};
+
+/// Search the package indexes of the project for packages matching `term`.
+pub fn search(term: String, offline: bool) -> Result<(), TackleError> {
+    if !is_initialized() {
+        return Err(TackleError::NotInitialized);
+    }
+    let workdir = get_project_root()?;
+    let manifest = read_manifest(&workdir)?;
+    if manifest.settings.indexes.is_empty() {
+        println!("No package indexes configured.");
+        return Ok(());
+    }
+
+    let mut found = 0;
+    for url in &manifest.settings.indexes {
+        info!("Searching '{}'...", url);
+        let index = read_index(url, offline)?;
+        for (name, entry) in index.search(&term) {
+            found += 1;
+            println!();
+            println!("{} ({})", name, entry.url);
+            if let Some(description) = &entry.description {
+                println!("\t{}", description);
+            }
+            if !entry.hooks.is_empty() {
+                let hooks: Vec<_> = entry.hooks.iter().map(Hook::git_name).collect();
+                println!("\t{:<24}{}", "hooks", hooks.join(", "));
+            }
+            if !entry.tags.is_empty() {
+                println!("\t{:<24}{}", "tags", entry.tags.join(", "));
+            }
+        }
+    }
+    if found == 0 {
+        println!("No packages found matching '{}'.", term);
+    }
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use std::{collections::BTreeMap, path::Path};
+
+use git2::Repository;
+use log::info;
+
+use super::{delete_stale_packages, manifest_requirements, resolve_packages};
+use crate::{
+    errors::TackleError,
+    lockfile::{read_lockfile, write_lockfile},
+    package::{
+        cache::lookup_repository, download::commit_log, link::link_hooks, source::GitSource,
+    },
+    project::{get_project_root, is_initialized, read_manifest, write_manifest},
+    util::{is_local_package, PackageRevision},
+};
+
+/// Update installed packages to the newest commit matching their requested
+/// revision, moving their dependencies as needed. Updates every package if none
+/// is given. When `offline` is set, packages are updated only to the commits
+/// already in the cache.
+pub fn update(package: Option<String>, offline: bool) -> Result<(), TackleError> {
+    if !is_initialized() {
+        return Err(TackleError::NotInitialized);
+    }
+    update_packages(&get_project_root()?, package, offline)
+}
+
+/// Update installed packages in the project at `workdir`.
+fn update_packages(
+    workdir: &Path,
+    package: Option<String>,
+    offline: bool,
+) -> Result<(), TackleError> {
+    let mut manifest = read_manifest(workdir)?;
+    let mut lockfile = read_lockfile(workdir)?;
+    let urls = match package {
+        Some(package) if !manifest.hooks.contains_package(&package) => {
+            return Err(TackleError::PackageNotInstalled(package));
+        }
+        Some(package) => vec![package],
+        None => manifest.hooks.package_urls().into_iter().collect(),
+    };
+
+    let mut unlocked = Vec::new();
+    for url in urls {
+        let entry = manifest.hooks.find_package(&url).unwrap();
+        if is_local_package(&url) {
+            info!("'{}' is inside the project, skipping", url);
+            continue;
+        }
+        if let Some(PackageRevision::Commit(_)) = PackageRevision::parse(&entry.version) {
+            info!("'{}' is pinned to a commit, skipping", url);
+            continue;
+        }
+        unlocked.push(url);
+    }
+    if unlocked.is_empty() {
+        return Ok(());
+    }
+
+    // dependencies of the updated packages may move as well
+    let old_commits: BTreeMap<_, _> = lockfile
+        .packages
+        .iter()
+        .map(|locked| (locked.url.clone(), locked.commit.clone()))
+        .collect();
+    let requirements = manifest_requirements(&manifest);
+    let (fetched, stale) =
+        resolve_packages(workdir, &mut manifest, &mut lockfile, &requirements, &unlocked, offline)?;
+    for (url, fetched) in fetched.iter().filter(|(url, _)| !is_local_package(url)) {
+        let old_commit = match old_commits.get(url) {
+            Some(commit) => commit,
+            None => {
+                info!("Added '{}' ({})", url, fetched.label());
+                continue;
+            }
+        };
+        if fetched.commit == *old_commit {
+            if unlocked.contains(url) {
+                info!("'{}' is up to date", url);
+            }
+            continue;
+        }
+        info!("Updated '{}' from {:.7} to {}", url, old_commit, fetched.label());
+        // vendored packages have no repository to read the log from
+        let source = GitSource::parse(url)?;
+        let repository =
+            lookup_repository(&source)?.and_then(|path| Repository::open_bare(path).ok());
+        if let Some(repository) = repository {
+            for line in commit_log(&repository, old_commit, &fetched.commit)? {
+                println!("\t{}", line);
+            }
+        }
+    }
+
+    write_manifest(workdir, &manifest)?;
+    write_lockfile(workdir, &lockfile)?;
+    delete_stale_packages(workdir, &stale, &lockfile)?;
+    link_hooks(workdir, &manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::update_packages;
+    use crate::{
+        cli::install_package,
+        lockfile::read_lockfile,
+        project::read_manifest,
+        testing::{commit_files, init_origin, init_project, tag_commit, use_test_cache},
+    };
+
+    #[test]
+    fn test_update_within_range() {
+        use_test_cache();
+        let project = init_project();
+        let workdir = project.path();
+        let (_origin, repository, url) = init_origin(".");
+        let hooks = "[hooks]\npre-commit = [{ command = [\"true\"] }]\n";
+        let commit = commit_files(&repository, &[("package.toml", hooks)]);
+        tag_commit(&repository, "v1.0.0", &commit);
+        install_package(workdir, &format!("{}@^1", url), false).unwrap();
+        let mut commits = Vec::new();
+        for version in ["v1.1.0", "v2.0.0"] {
+            let commit = commit_files(&repository, &[("CHANGELOG", version)]);
+            tag_commit(&repository, version, &commit);
+            commits.push(commit);
+        }
+
+        // the pin moves to the newest version matching the range, not the newest version
+        update_packages(workdir, None, false).unwrap();
+        let manifest = read_manifest(workdir).unwrap();
+        let entry = manifest.hooks.find_package(&url).unwrap();
+        assert_eq!(entry.version, "^1");
+        assert_eq!(entry.commit, commits[0]);
+        let lockfile = read_lockfile(workdir).unwrap();
+        let locked = lockfile.get(&url).unwrap();
+        assert_eq!(locked.commit, commits[0]);
+        assert_eq!(locked.version.as_deref(), Some("v1.1.0"));
+    }
+}
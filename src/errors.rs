@@ -1,5 +1,4 @@
 //! Defines the application error type and error handling methods.
-use log::error;
 use thiserror::Error;
 
 /// Enum representing the possible errors that can occur when interacting with the git hook manager.
@@ -15,21 +14,146 @@ pub enum TackleError {
     #[error("Failed to read the manifest file")]
     ManifestReadFailed,
     /// An error occured while parsing the manifest file.
-    #[error("Failed to parse the manifest file")]
-    ManifestParseFailed(#[from] toml::de::Error),
+    #[error("Failed to parse the manifest file: {0}")]
+    ManifestParseFailed(toml::de::Error),
     /// An error occured while creating the tackle directory.
     #[error("Failed to create the .tackle directory")]
-    CreateTackleDirectoryFailed(#[from] std::io::Error),
+    CreateTackleDirectoryFailed(std::io::Error),
+    /// The home directory holding the cache could not be found.
+    #[error("Failed to locate the home directory, set TACKLE_HOME to the cache directory")]
+    CacheDirectoryNotFound,
+    /// A directory of the cache could not be created or removed.
+    #[error("Failed to write to the cache at '{path}': {source}")]
+    CacheWriteFailed { path: std::path::PathBuf, source: std::io::Error },
     /// An error occured while creating the hooks directory.
     #[error("Cannot initialize an existing project")]
     AlreadyInitialized,
     /// Attempted to perform an operation on a non-existent project.
     #[error("Cannot perform operation on a non-initialized project")]
     NotInitialized,
-    /// Attempted to use an invalid commit hook.
-    #[error("Invalid commit hook")]
-    InvalidCommitHook,
+    /// Attempted to use an unknown git hook.
+    #[error("Unknown git hook '{0}', expected one of: {}", crate::hooks::Hook::ALL.map(|hook| hook.git_name()).join(", "))]
+    UnknownHook(String),
+    /// The package is neither a repository URL nor a local path.
+    #[error("Invalid package '{0}', expected 'owner/repo', a URL, or a './' path")]
+    InvalidPackageUrl(String),
     /// An error occured while cloning the repository.
     #[error("Repository clone failed")]
     RepositoryCloneFailed,
+    /// An error occured while fetching an existing repository.
+    #[error("Repository fetch failed")]
+    RepositoryFetchFailed,
+    /// The target commit does not exist in the repository.
+    #[error("Commit '{0}' not found in the repository")]
+    CommitNotFound(String),
+    /// An error occured while reading the lockfile.
+    #[error("Failed to read the lockfile")]
+    LockfileReadFailed,
+    /// An error occured while parsing the lockfile.
+    #[error("Failed to parse the lockfile: {0}")]
+    LockfileParseFailed(toml::de::Error),
+    /// An error occured while writing the lockfile.
+    #[error("Failed to write the lockfile")]
+    LockfileWriteFailed,
+    /// The lockfile does not match the manifest.
+    #[error("The lockfile is out of date with the manifest for: {0}")]
+    LockfileOutOfDate(String),
+    /// The target branch does not exist in the repository.
+    #[error("Branch '{0}' not found in the repository")]
+    BranchNotFound(String),
+    /// The version specifier is neither a tag nor a semver range.
+    #[error("Invalid version '{0}', expected a tag name or a semver range")]
+    InvalidVersion(String),
+    /// No tag in the repository matches the version specifier.
+    #[error("No tag matches the version '{0}'")]
+    NoMatchingVersion(String),
+    /// The package does not contain a `package.toml` manifest.
+    #[error("Package '{0}' does not contain a package.toml manifest")]
+    PackageManifestNotFound(String),
+    /// The manifest of a package could not be read.
+    #[error("Failed to read the package manifest '{file}': {source}")]
+    PackageReadFailed { file: std::path::PathBuf, source: std::io::Error },
+    /// The manifest of a package is not valid TOML, or does not match the schema.
+    #[error("Failed to parse '{file}' at line {line}, column {column}: {message}")]
+    PackageParseFailed { file: std::path::PathBuf, line: usize, column: usize, message: String },
+    /// Linting a package found errors.
+    #[error("Found {0} error(s) in the package manifest")]
+    PackageLintFailed(usize),
+    /// Attempted to generate a package from an unknown template.
+    #[error("Unknown template '{0}', expected one of: {}", crate::package::scaffold::Template::ALL.map(|template| template.name()).join(", "))]
+    UnknownTemplate(String),
+    /// Attempted to generate a package with a name unsafe to write into its files.
+    #[error("Invalid package name '{0}', expected letters, digits, '-', '_' or '.'")]
+    InvalidPackageName(String),
+    /// Attempted to generate a package into a directory with files in it.
+    #[error("Cannot create a package in '{0}', the directory is not empty")]
+    DirectoryNotEmpty(std::path::PathBuf),
+    /// A file of a new package could not be written.
+    #[error("Failed to write '{file}': {source}")]
+    PackageWriteFailed { file: std::path::PathBuf, source: std::io::Error },
+    /// An error occured while creating a git repository.
+    #[error("Failed to initialize a git repository")]
+    RepositoryInitFailed,
+    /// An option set for a package does not match the options it declares.
+    #[error("Invalid option '{option}' for '{package}': {reason}")]
+    InvalidOption { package: String, option: String, reason: String },
+    /// A manifest entry selects a hook the package does not define.
+    #[error("Package '{package}' has no '{hook}' hook with the ID '{id}'")]
+    UnknownHookId { package: String, hook: crate::hooks::Hook, id: String },
+    /// The package does not define any hooks.
+    #[error("Package '{0}' does not define any hooks")]
+    PackageHasNoHooks(String),
+    /// The contents of an installed package do not match its recorded hash.
+    #[error("Integrity check failed for '{package}': expected {expected}, found {actual}")]
+    IntegrityMismatch { package: String, expected: String, actual: String },
+    /// A file of an installed package could not be read to compute its hash.
+    #[error("Failed to read '{file}' to check its integrity: {source}")]
+    IntegrityReadFailed { file: std::path::PathBuf, source: std::io::Error },
+    /// Attempted to use a package that is not installed in the project.
+    #[error("Package '{0}' is not installed")]
+    PackageNotInstalled(String),
+    /// A condition refers to a hook which is not in the pipeline.
+    #[error("Hook '{hook}' refers to unknown hook '{reference}'")]
+    UnknownHookReference { hook: String, reference: String },
+    /// A condition refers to a hook ID defined by more than one package.
+    #[error(
+        "Hook '{hook}' refers to '{reference}', which matches {candidates}; \
+         use a qualified 'package:hook' ID"
+    )]
+    AmbiguousHookReference { hook: String, reference: String, candidates: String },
+    /// Hooks refer to each other in their conditions in a cycle.
+    #[error("Hooks refer to each other in a cycle: {0}")]
+    HookCycle(String),
+    /// A blocking hook exited with a non-zero error code.
+    #[error("Blocking hook '{0}' failed")]
+    BlockingHookFailed(String),
+    /// A package is neither in the cache nor vendored while offline.
+    #[error(
+        "Cannot install '{package}' offline, {commit} is not in the cache and has no vendored copy"
+    )]
+    PackageNotCached { package: String, commit: String },
+    /// A revision of a package could not be resolved from the cached repository.
+    #[error("Failed to resolve {commit} of '{package}' from the cache: {source}")]
+    CachedRevisionFailed { package: String, commit: String, source: Box<TackleError> },
+    /// The catalogue of a package index could not be read.
+    #[error("Failed to read the package index '{0}': {1}")]
+    IndexParseFailed(String, String),
+    /// A bare package name is not listed in any package index.
+    #[error("Package '{0}' was not found in any configured package index")]
+    PackageNotInIndex(String),
+    /// No revision of a package satisfies the requirements of its dependents.
+    #[error("No version of '{package}' satisfies every requirement: {requirements}")]
+    DependencyConflict { package: String, requirements: String },
+    /// Packages depend on each other in a cycle.
+    #[error("Dependency cycle detected: {0}")]
+    DependencyCycle(String),
+    /// A shim in the git hooks directory could not be written or removed.
+    #[error("Failed to update the git hook '{path}': {source}")]
+    HookLinkFailed { path: std::path::PathBuf, source: std::io::Error },
+    /// A package directory of the project could not be linked or removed.
+    #[error("Failed to link the package directory '{path}': {source}")]
+    PackageLinkFailed { path: std::path::PathBuf, source: std::io::Error },
+    /// A foreign git hook could not be backed up as a backup already exists.
+    #[error("Cannot back up existing git hook, '{0}' already exists")]
+    HookBackupExists(std::path::PathBuf),
 }
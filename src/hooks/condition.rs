@@ -1,4 +1,77 @@
 //! Handles hook conditions.
+use std::path::Path;
+
+use git2::Repository;
+
 use crate::{package::HookDefinition, util::is_program_in_path};
 
 use super::{HookRunner, HookState, HookWithState};
+
+/// Get the branch checked out in the repository at `workdir`, if any.
+pub fn current_branch(workdir: &Path) -> Option<String> {
+    let repository = Repository::discover(workdir).ok()?;
+    let head = repository.head().ok()?;
+    head.shorthand().map(|branch| branch.to_owned())
+}
+
+impl HookRunner {
+    /// Test if the hook at the given index is in the target state.
+    fn has_hook_state(&self, index: usize, state: &HookState) -> bool {
+        &self.hooks[index].state == state
+    }
+
+    /// Test if every hook referred to by the conditions of a hook has finished.
+    pub fn has_finished_references(&self, hook: &HookWithState) -> bool {
+        hook.references.iter().flat_map(|references| references.requirements()).all(|(index, _)| {
+            !matches!(self.hooks[index].state, HookState::Pending | HookState::Running)
+        })
+    }
+
+    /// Test if the OS-level dependencies of a hook are available.
+    fn has_dependencies(hook: &HookDefinition) -> bool {
+        hook.dependencies.iter().all(|dep| is_program_in_path(dep))
+    }
+
+    /// Test if a hook is ready to run.
+    pub fn is_matching_hook(&self, hook: &HookWithState) -> bool {
+        // ensure that the hook is pending
+        if hook.state != HookState::Pending {
+            return false;
+        }
+
+        // ensure that hook has os dependencies
+        if !Self::has_dependencies(&hook.hook) {
+            return false;
+        }
+
+        // hooks without conditions always run
+        let conditions = &hook.hook.conditions;
+        if conditions.is_empty() {
+            return true;
+        }
+
+        // check all conditions, with the hooks they refer to resolved
+        conditions.iter().zip(&hook.references).any(|(condition, references)| {
+            let matches_skip = references
+                .skipped
+                .iter()
+                .all(|index| self.has_hook_state(*index, &HookState::Skipped));
+            let matches_success = references
+                .successful
+                .iter()
+                .all(|index| self.has_hook_state(*index, &HookState::Successful));
+            let matches_failed = references
+                .failed
+                .iter()
+                .all(|index| self.has_hook_state(*index, &HookState::Failed));
+            let matches_exists =
+                condition.exists.iter().all(|file| std::fs::metadata(file).is_ok());
+            let matches_branch = condition
+                .branch
+                .iter()
+                .all(|target| self.branch.as_deref() == Some(target.as_str()));
+
+            matches_skip && matches_success && matches_failed && matches_exists && matches_branch
+        })
+    }
+}
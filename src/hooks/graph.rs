@@ -0,0 +1,229 @@
+//! Checks the graph of hooks formed by the references of their conditions.
+use log::warn;
+
+use crate::errors::TackleError;
+
+use super::{reference::ConditionReferences, HookRunner, HookState, HookWithState};
+
+impl HookWithState {
+    /// The hooks this hook refers to in any of its conditions.
+    fn referenced_hooks(&self) -> impl Iterator<Item = usize> + '_ {
+        self.references.iter().flat_map(|references| references.requirements()).map(|(i, _)| i)
+    }
+}
+
+impl HookRunner {
+    /// Find a cycle of hooks referring to each other, returned as the indices
+    /// of the hooks along it, starting and ending with the same hook.
+    fn find_cycle(&self) -> Option<Vec<usize>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Visit {
+            New,
+            Active,
+            Done,
+        }
+
+        fn visit(
+            runner: &HookRunner,
+            index: usize,
+            visits: &mut [Visit],
+            path: &mut Vec<usize>,
+        ) -> Option<Vec<usize>> {
+            visits[index] = Visit::Active;
+            path.push(index);
+            for next in runner.hooks[index].referenced_hooks() {
+                match visits[next] {
+                    Visit::Active => {
+                        let start = path.iter().position(|hook| *hook == next).unwrap();
+                        let mut cycle = path[start..].to_vec();
+                        cycle.push(next);
+                        return Some(cycle);
+                    }
+                    Visit::New => {
+                        if let Some(cycle) = visit(runner, next, visits, path) {
+                            return Some(cycle);
+                        }
+                    }
+                    Visit::Done => {}
+                }
+            }
+            path.pop();
+            visits[index] = Visit::Done;
+            None
+        }
+
+        let mut visits = vec![Visit::New; self.hooks.len()];
+        (0..self.hooks.len()).find_map(|index| match visits[index] {
+            Visit::New => visit(self, index, &mut visits, &mut Vec::new()),
+            _ => None,
+        })
+    }
+
+    /// Explain why a condition can never match, given the hooks which are
+    /// already known to never run.
+    fn unsatisfiable_reason(&self, references: &ConditionReferences) -> Option<String> {
+        let mut required: Vec<(usize, HookState)> = Vec::new();
+        for (index, state) in references.requirements() {
+            let id = self.hooks[index].qualified_id();
+            if let Some((_, other)) = required.iter().find(|(other, _)| *other == index) {
+                if *other != state {
+                    return Some(format!("'{}' cannot be both {} and {}", id, other, state));
+                }
+            }
+            match &self.hooks[index].state {
+                // the hook has not finished yet
+                HookState::Pending | HookState::Running => {}
+                HookState::Blocked(_) => return Some(format!("'{}' is blocked", id)),
+                // disabled by the project
+                finished if *finished != state => {
+                    return Some(format!("'{}' is disabled, so it is never {}", id, state));
+                }
+                _ => {}
+            }
+            required.push((index, state));
+        }
+        None
+    }
+
+    /// Check the graph of references between hooks before running them.
+    /// Hooks which refer to each other in a cycle are an error. Hooks whose
+    /// conditions can never match, for instance because they refer to
+    /// disabled hooks, are blocked, along with the hooks waiting for them.
+    /// Hooks wait for the hooks they refer to wherever those appear in the
+    /// pipeline, so a reference to a later hook is only reported, by blocking
+    /// the hook making it, once the later hook can never run.
+    pub fn check_graph(&mut self) -> Result<(), TackleError> {
+        if let Some(cycle) = self.find_cycle() {
+            let ids: Vec<_> = cycle.iter().map(|index| self.hooks[*index].qualified_id()).collect();
+            return Err(TackleError::HookCycle(ids.join(" -> ")));
+        }
+        // blocking a hook may make the conditions of other hooks unsatisfiable
+        loop {
+            let blocked = self.hooks.iter().enumerate().find_map(|(index, hook)| {
+                if hook.state != HookState::Pending || hook.references.is_empty() {
+                    return None;
+                }
+                let reasons = hook
+                    .references
+                    .iter()
+                    .map(|references| self.unsatisfiable_reason(references))
+                    .collect::<Option<Vec<_>>>()?;
+                Some((index, reasons.join("; ")))
+            });
+            match blocked {
+                Some((index, reason)) => {
+                    let hook = &mut self.hooks[index];
+                    warn!("Hook '{}' can never run: {}", hook.qualified_id(), reason);
+                    hook.state = HookState::Blocked(reason);
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::TackleError,
+        hooks::{Hook, HookRunner, HookState},
+        package::Package,
+    };
+
+    #[test]
+    fn test_check_graph() {
+        let load = |toml: &str| -> Result<HookRunner, TackleError> {
+            let package: Package = toml::from_str(toml).unwrap();
+            HookRunner::from_hooks(package.hooks.into_hook(Hook::PreCommit))
+        };
+
+        let cycle = load(
+            r#"
+            [[hooks.pre-commit]]
+            id = "a"
+            command = ["true"]
+            conditions = [{ successful = ["b"] }]
+
+            [[hooks.pre-commit]]
+            id = "b"
+            command = ["true"]
+            conditions = [{}, { failed = ["a"] }]
+            "#,
+        );
+        match cycle {
+            Err(TackleError::HookCycle(cycle)) => {
+                assert_eq!(cycle, "project:a -> project:b -> project:a")
+            }
+            _ => panic!("expected a cycle"),
+        }
+
+        let mut runner = load(
+            r#"
+            [[hooks.pre-commit]]
+            id = "lint"
+            command = ["false"]
+            blocking = false
+
+            [[hooks.pre-commit]]
+            id = "never"
+            command = ["true"]
+            conditions = [{ successful = ["lint"], failed = ["lint"] }]
+
+            [[hooks.pre-commit]]
+            id = "after-never"
+            command = ["true"]
+            conditions = [{ skipped = ["never"] }]
+
+            [[hooks.pre-commit]]
+            id = "report"
+            command = ["true"]
+            conditions = [{ successful = ["lint"] }, { skipped = ["never"] }]
+
+            [[hooks.pre-commit]]
+            id = "fallback"
+            command = ["true"]
+            conditions = [{ skipped = ["report"] }]
+            "#,
+        )
+        .unwrap();
+        let reason = |runner: &HookRunner, index: usize| match &runner.hooks[index].state {
+            HookState::Blocked(reason) => reason.clone(),
+            state => panic!("expected a blocked hook, found {:?}", state),
+        };
+        assert_eq!(reason(&runner, 1), "'project:lint' cannot be both successful and failed");
+        assert_eq!(reason(&runner, 2), "'project:never' is blocked");
+        assert_eq!(runner.hooks[3].state, HookState::Pending);
+
+        // hooks run once the hooks they refer to have finished, and blocked
+        // hooks do not fail the run even though they are blocking
+        assert!(runner.run(&[], None, 1).is_ok());
+        assert_eq!(runner.hooks[0].state, HookState::Failed);
+        assert_eq!(runner.hooks[3].state, HookState::Skipped);
+        assert_eq!(runner.hooks[4].state, HookState::Successful);
+
+        // hooks wait for the hooks they refer to, even those which appear later,
+        // but not for later hooks which can never run
+        let mut runner = load(
+            r#"
+            [[hooks.pre-commit]]
+            id = "early"
+            command = ["true"]
+            conditions = [{ successful = ["late"] }]
+
+            [[hooks.pre-commit]]
+            id = "late"
+            command = ["true"]
+            conditions = [{ successful = ["base"], failed = ["base"] }]
+
+            [[hooks.pre-commit]]
+            id = "base"
+            command = ["true"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(reason(&runner, 0), "'project:late' is blocked");
+        assert_eq!(reason(&runner, 1), "'project:base' cannot be both successful and failed");
+        assert!(runner.run(&[], None, 1).is_ok());
+        assert_eq!(runner.hooks[2].state, HookState::Successful);
+    }
+}
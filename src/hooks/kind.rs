@@ -0,0 +1,121 @@
+//! Defines the git hooks supported by Tackle.
+use std::{fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::errors::TackleError;
+
+/// A client-side git hook.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Hook {
+    ApplypatchMsg,
+    PreApplypatch,
+    PostApplypatch,
+    PreCommit,
+    PreMergeCommit,
+    PrepareCommitMsg,
+    CommitMsg,
+    PostCommit,
+    PreRebase,
+    PostCheckout,
+    PostMerge,
+    PrePush,
+    PostRewrite,
+    PreAutoGc,
+    ReferenceTransaction,
+}
+
+impl Hook {
+    /// Every hook supported by Tackle.
+    pub const ALL: [Hook; 15] = [
+        Hook::ApplypatchMsg,
+        Hook::PreApplypatch,
+        Hook::PostApplypatch,
+        Hook::PreCommit,
+        Hook::PreMergeCommit,
+        Hook::PrepareCommitMsg,
+        Hook::CommitMsg,
+        Hook::PostCommit,
+        Hook::PreRebase,
+        Hook::PostCheckout,
+        Hook::PostMerge,
+        Hook::PrePush,
+        Hook::PostRewrite,
+        Hook::PreAutoGc,
+        Hook::ReferenceTransaction,
+    ];
+
+    /// The name of the hook file git executes.
+    pub fn git_name(&self) -> &'static str {
+        match self {
+            Hook::ApplypatchMsg => "applypatch-msg",
+            Hook::PreApplypatch => "pre-applypatch",
+            Hook::PostApplypatch => "post-applypatch",
+            Hook::PreCommit => "pre-commit",
+            Hook::PreMergeCommit => "pre-merge-commit",
+            Hook::PrepareCommitMsg => "prepare-commit-msg",
+            Hook::CommitMsg => "commit-msg",
+            Hook::PostCommit => "post-commit",
+            Hook::PreRebase => "pre-rebase",
+            Hook::PostCheckout => "post-checkout",
+            Hook::PostMerge => "post-merge",
+            Hook::PrePush => "pre-push",
+            Hook::PostRewrite => "post-rewrite",
+            Hook::PreAutoGc => "pre-auto-gc",
+            Hook::ReferenceTransaction => "reference-transaction",
+        }
+    }
+
+    /// Test if git passes input to the hook on stdin.
+    pub fn reads_stdin(&self) -> bool {
+        matches!(self, Hook::PrePush | Hook::PostRewrite | Hook::ReferenceTransaction)
+    }
+}
+
+impl FromStr for Hook {
+    type Err = TackleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // accept the legacy names without dashes, e.g. `precommit`
+        Hook::ALL
+            .into_iter()
+            .find(|hook| hook.git_name() == s || hook.git_name().replace('-', "") == s)
+            .ok_or_else(|| TackleError::UnknownHook(s.to_owned()))
+    }
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.git_name())
+    }
+}
+
+impl Serialize for Hook {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.git_name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Hook {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Hook;
+
+    #[test]
+    fn test_parse_hook() {
+        assert_eq!("pre-commit".parse::<Hook>().unwrap(), Hook::PreCommit);
+        assert_eq!("precommit".parse::<Hook>().unwrap(), Hook::PreCommit);
+        assert_eq!("commit-msg".parse::<Hook>().unwrap(), Hook::CommitMsg);
+        assert_eq!("post-checkout".parse::<Hook>().unwrap(), Hook::PostCheckout);
+        assert!("postpush".parse::<Hook>().is_err());
+        for hook in Hook::ALL {
+            assert_eq!(hook.git_name().parse::<Hook>().unwrap(), hook);
+        }
+    }
+}
@@ -1,19 +1,60 @@
 //! Contains the logic for the execution of hook pipelines.
+use std::{fmt, path::PathBuf, process::Command};
 
-use crate::{package::HookDefinition, util::is_program_in_path};
+use crate::{
+    package::{
+        hook_ids,
+        options::{option_env, substitute_command, OptionValues},
+        HookDefinition,
+    },
+    project::EffectiveHook,
+};
 pub mod condition;
+pub mod graph;
+pub mod kind;
+pub mod reference;
+pub mod scheduler;
+
+pub use kind::Hook;
+
+use self::reference::{ConditionReferences, Namespaces};
+
+/// The environment variable exposing the package directory to hook commands.
+pub static PACKAGE_DIR_ENV: &str = "TACKLE_PACKAGE_DIR";
+
+/// The namespace of the hooks defined inline in the project manifest.
+pub static PROJECT_NAMESPACE: &str = "project";
 
 /// An enum of possible hook states.
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug)]
 pub enum HookState {
     /// The hook was successful.
     Successful,
     /// The hook exited with a non-zero error code.
     Failed,
-    /// The hook was skipped as its conditions were not met.
+    /// The hook was skipped as its conditions were not met, or it is
+    /// disabled by the project.
     Skipped,
     /// The hook is waiting to be run.
     Pending,
+    /// The hook is running.
+    Running,
+    /// The hook can never run, for the given reason. Like skipped hooks,
+    /// blocked hooks do not fail the pipeline, even if they are blocking.
+    Blocked(String),
+}
+
+impl fmt::Display for HookState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HookState::Successful => "successful",
+            HookState::Failed => "failed",
+            HookState::Skipped => "skipped",
+            HookState::Pending => "pending",
+            HookState::Running => "running",
+            HookState::Blocked(_) => "blocked",
+        })
+    }
 }
 
 /// Wrapper struct for a hook definition, containing the hook definition and
@@ -22,103 +63,172 @@ pub enum HookState {
 pub struct HookWithState {
     hook: HookDefinition,
     state: HookState,
+    /// The ID of the hook inside its package, generated for hooks without one.
+    id: String,
+    /// The index of the package defining this hook, in the order packages
+    /// were first added to the runner.
+    package: usize,
+    /// The name of the package defining this hook.
+    namespace: String,
+    /// The URL of the package defining this hook.
+    url: Option<String>,
+    /// The hooks each condition refers to, once resolved.
+    references: Vec<ConditionReferences>,
+    /// The directory of the package defining this hook.
+    directory: Option<PathBuf>,
+    /// Extra environment variables passed to the hook command.
+    env: Vec<(String, String)>,
 }
 
 /// The hook runner runs hooks!
+#[derive(Default)]
 pub struct HookRunner {
     hooks: Vec<HookWithState>,
+    /// The number of packages added to the runner.
+    packages: usize,
+    /// The namespaces taken by the packages added to the runner.
+    namespaces: Namespaces,
+    /// The branch checked out in the project, matched against the `branch`
+    /// conditions of hooks.
+    branch: Option<String>,
+}
+
+/// The package a group of hooks is added to the runner from.
+pub struct HookSource<'a> {
+    /// The name of the package, used to qualify the IDs of its hooks unless
+    /// another package has taken it.
+    pub namespace: &'a str,
+    /// The URL the package is installed from, if any.
+    pub url: Option<&'a str>,
+    /// The directory hook commands are resolved against.
+    pub directory: Option<PathBuf>,
 }
 
 impl HookRunner {
-    /// Creates a new hook runner.
-    pub fn from_hooks(hooks: Vec<HookDefinition>) -> HookRunner {
-        HookRunner {
-            hooks: hooks
-                .into_iter()
-                .map(|hook| HookWithState {
-                    hook,
-                    state: HookState::Pending,
-                })
-                .collect(),
+    /// Creates a new hook runner for a project with the given branch checked out.
+    pub fn new(branch: Option<String>) -> HookRunner {
+        HookRunner { branch, ..Default::default() }
+    }
+
+    /// Append the hooks of a package to the pipeline, configured with the
+    /// option values of the package. Inactive hooks are added as skipped, so
+    /// that other hooks can still refer to them. Hooks added from the same
+    /// source more than once belong to the same package.
+    pub fn add_hooks(
+        &mut self,
+        source: HookSource,
+        options: &OptionValues,
+        hooks: Vec<EffectiveHook>,
+    ) {
+        let env = option_env(options);
+        let namespace = self.namespaces.assign(source.namespace, source.url);
+        let existing = self
+            .hooks
+            .iter()
+            .find(|hook| hook.namespace == namespace && hook.url.as_deref() == source.url)
+            .map(|hook| hook.package);
+        let package = existing.unwrap_or_else(|| {
+            self.packages += 1;
+            self.packages - 1
+        });
+        // generate IDs which do not clash with the earlier hooks of the package
+        let mut definitions: Vec<_> = self
+            .hooks
+            .iter()
+            .filter(|hook| hook.package == package)
+            .map(|hook| hook.hook.clone())
+            .collect();
+        let added = definitions.len();
+        definitions.extend(hooks.iter().map(|hook| hook.definition.clone()));
+        let ids = hook_ids(&definitions).split_off(added);
+        self.hooks.extend(hooks.into_iter().zip(ids).map(|(effective, id)| {
+            let mut hook = effective.definition;
+            hook.command = substitute_command(&hook.command, options);
+            HookWithState {
+                hook,
+                state: match effective.active {
+                    true => HookState::Pending,
+                    false => HookState::Skipped,
+                },
+                id,
+                package,
+                namespace: namespace.clone(),
+                url: source.url.map(|url| url.to_owned()),
+                references: Vec::new(),
+                directory: source.directory.clone(),
+                env: env.clone(),
+            }
+        }));
+    }
+
+    /// Get the index of the next hook to run, passing over serial hooks when
+    /// `parallel_only` is set.
+    fn next_hook_index(&self, parallel_only: bool) -> Option<usize> {
+        self.hooks
+            .iter()
+            .position(|hook| !(parallel_only && hook.hook.serial) && self.is_matching_hook(hook))
+    }
+}
+
+impl HookWithState {
+    /// Build the command of the hook, appending the arguments from git.
+    /// Returns `None` for hooks with an empty command.
+    fn command(&self, args: &[String]) -> Option<Command> {
+        let (program, rest) = self.hook.command.split_first()?;
+        let mut command = match &self.directory {
+            // resolve relative programs against the package directory
+            Some(directory) if program.starts_with("./") => Command::new(directory.join(program)),
+            _ => Command::new(program),
+        };
+        command.args(rest).args(args);
+        if let Some(directory) = &self.directory {
+            command.env(PACKAGE_DIR_ENV, directory);
         }
+        command.envs(self.env.iter().map(|(name, value)| (name, value)));
+        Some(command)
     }
+}
 
-    /// Set the state of a hook.
+#[cfg(test)]
+impl HookRunner {
+    /// Creates a new hook runner from the hooks of the project, resolving and
+    /// checking the references between them.
+    pub fn from_hooks(
+        hooks: Vec<HookDefinition>,
+    ) -> Result<HookRunner, crate::errors::TackleError> {
+        let mut runner = HookRunner::default();
+        let source = HookSource { namespace: PROJECT_NAMESPACE, url: None, directory: None };
+        let hooks = hooks
+            .into_iter()
+            .map(|definition| EffectiveHook { definition, active: true, overridden: Vec::new() })
+            .collect();
+        runner.add_hooks(source, &OptionValues::new(), hooks);
+        runner.resolve_references()?;
+        runner.check_graph()?;
+        Ok(runner)
+    }
+
+    /// Set the state of the hook with the given qualified ID.
     pub fn set_hook_state(&mut self, hook_id: &str, state: HookState) {
         let hook = self
             .hooks
             .iter_mut()
-            .find(|hook| hook.hook.id == Some(hook_id.to_owned()))
+            .find(|hook| hook.qualified_id() == hook_id)
             .expect("Hook not found");
 
         hook.state = state;
     }
 
-    /// Test if a hook is successful.
-    pub fn is_matching_hook(&self, hook: &HookWithState) -> bool {
-        // ensure that the hook is pending
-        if hook.state != HookState::Pending {
-            return false;
-        }
-
-        // ensure that hook has os dependencies
-        if !hook.hook.dependencies.is_empty() {
-            if !hook
-                .hook
-                .dependencies
-                .iter()
-                .all(|dep| is_program_in_path(dep))
-            {
-                return false;
-            }
-        }
-
-        let repository = git2::Repository::discover(std::env::current_dir().unwrap()).unwrap();
-
-        // check all conditions
-        let conditions = &hook.hook.conditions;
-        conditions.iter().any(|condition| {
-            let matches_skip = condition.skipped.iter().all(|hook_id| {
-                self.hooks.iter().any(|hook| {
-                    hook.hook.id == Some(hook_id.clone()) && hook.state == HookState::Skipped
-                })
-            });
-            let matches_success = condition.successful.iter().all(|hook_id| {
-                self.hooks.iter().any(|hook| {
-                    hook.hook.id == Some(hook_id.clone()) && hook.state == HookState::Successful
-                })
-            });
-            let matches_failed = condition.failed.iter().all(|hook_id| {
-                self.hooks.iter().any(|hook| {
-                    hook.hook.id == Some(hook_id.clone()) && hook.state == HookState::Failed
-                })
-            });
-            let matches_exists = condition
-                .exists
-                .iter()
-                .all(|file| std::fs::metadata(file).is_ok());
-            let matches_branch = condition
-                .branch
-                .iter()
-                .all(|branch| repository.head().unwrap().shorthand().unwrap() == branch);
-
-            matches_skip && matches_success && matches_failed && matches_exists && matches_branch
-        })
-    }
-
     /// Get the next hook to run, respecting the hook order and conditions.
     pub fn next_hook(&mut self) -> Option<&HookDefinition> {
-        self.hooks
-            .iter()
-            .filter(|hook| self.is_matching_hook(hook))
-            .next()
-            .map(|hook| &hook.hook)
+        self.next_hook_index(false).map(|index| &self.hooks[index].hook)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
+        errors::TackleError,
         hooks::HookState,
         package::{HookCondition, HookDefinition},
     };
@@ -132,6 +242,8 @@ mod tests {
                 id: Some("example-1".to_string()),
                 command: vec!["echo".to_string(), "Hello, world!".to_string()],
                 dependencies: vec![],
+                blocking: true,
+                serial: false,
                 conditions: vec![HookCondition {
                     successful: vec![],
                     failed: vec![],
@@ -144,6 +256,8 @@ mod tests {
                 id: Some("example-2".to_string()),
                 command: vec!["echo".to_string(), "Hello, world!".to_string()],
                 dependencies: vec![],
+                blocking: true,
+                serial: false,
                 conditions: vec![HookCondition {
                     successful: vec!["example-1".to_string()],
                     failed: vec![],
@@ -156,6 +270,8 @@ mod tests {
                 id: Some("example-3".to_string()),
                 command: vec!["echo".to_string(), "Hello, world!".to_string()],
                 dependencies: vec![],
+                blocking: true,
+                serial: false,
                 conditions: vec![HookCondition {
                     successful: vec!["example-1".to_string()],
                     failed: vec!["example-2".to_string()],
@@ -164,18 +280,82 @@ mod tests {
                     exists: vec![],
                 }],
             },
-        ]);
-
-        assert_eq!(
-            hook_runner.next_hook().unwrap().id,
-            Some("example-1".to_string())
-        );
-        hook_runner.set_hook_state("example-1", HookState::Successful);
-        assert_eq!(
-            hook_runner.next_hook().unwrap().id,
-            Some("example-2".to_string())
-        );
-        hook_runner.set_hook_state("example-2", HookState::Successful);
+        ])
+        .unwrap();
+
+        assert_eq!(hook_runner.next_hook().unwrap().id, Some("example-1".to_string()));
+        hook_runner.set_hook_state("project:example-1", HookState::Successful);
+        assert_eq!(hook_runner.next_hook().unwrap().id, Some("example-2".to_string()));
+        hook_runner.set_hook_state("project:example-2", HookState::Successful);
         assert_eq!(hook_runner.next_hook(), None);
+
+        // branch conditions match the branch the runner was created for
+        let on_branch = |branch: &str| HookDefinition {
+            id: Some(branch.to_string()),
+            command: vec!["echo".to_string()],
+            dependencies: vec![],
+            blocking: true,
+            serial: false,
+            conditions: vec![HookCondition {
+                successful: vec![],
+                failed: vec![],
+                skipped: vec![],
+                branch: vec![branch.to_string()],
+                exists: vec![],
+            }],
+        };
+        let mut hook_runner =
+            HookRunner::from_hooks(vec![on_branch("dev"), on_branch("main")]).unwrap();
+        assert_eq!(hook_runner.next_hook(), None);
+        hook_runner.branch = Some("main".to_string());
+        assert_eq!(hook_runner.next_hook().unwrap().id, Some("main".to_string()));
+    }
+
+    #[test]
+    fn test_hook_runner_run() {
+        let hook = |id: &str, command: &str, blocking: bool, failed: Vec<String>| HookDefinition {
+            id: Some(id.to_string()),
+            command: vec![command.to_string()],
+            dependencies: vec![],
+            blocking,
+            serial: false,
+            conditions: vec![HookCondition {
+                successful: vec![],
+                failed,
+                skipped: vec![],
+                branch: vec![],
+                exists: vec![],
+            }],
+        };
+        let mut hook_runner = HookRunner::from_hooks(vec![
+            hook("lint", "false", false, vec![]),
+            hook("report", "true", true, vec!["lint".to_string()]),
+        ])
+        .unwrap();
+        assert!(hook_runner.run(&[], None, 1).is_ok());
+        assert_eq!(hook_runner.hooks[0].state, HookState::Failed);
+        assert_eq!(hook_runner.hooks[1].state, HookState::Successful);
+
+        let mut hook_runner = HookRunner::from_hooks(vec![
+            hook("lint", "false", true, vec![]),
+            hook("format", "true", true, vec![]),
+            hook("report", "true", true, vec!["format".to_string()]),
+        ])
+        .unwrap();
+        assert!(hook_runner.run(&[], Some(b"refs"), 1).is_err());
+        assert_eq!(hook_runner.hooks[2].state, HookState::Skipped);
+
+        // references to unknown hooks fail before any hook runs
+        let err = HookRunner::from_hooks(vec![
+            hook("lint", "false", true, vec![]),
+            hook("report", "true", true, vec!["format".to_string()]),
+        ])
+        .err()
+        .unwrap();
+        assert!(matches!(
+            err,
+            TackleError::UnknownHookReference { hook, reference }
+                if hook == "project:report" && reference == "format"
+        ));
     }
 }
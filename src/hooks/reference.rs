@@ -0,0 +1,223 @@
+//! Resolves the hook IDs that conditions refer to.
+//!
+//! Hooks are identified by their package and their ID inside it, written
+//! `package:hook`. The package is given by its name or by the URL it is
+//! installed from. A bare `hook` refers to the hook of the same package if
+//! there is one, and otherwise to the only hook in the pipeline with that ID.
+//!
+//! The hooks defined inline in the project manifest belong to the `project`
+//! package. A package whose name is `project`, or is already taken by another
+//! package in the pipeline, is named by its URL instead.
+use std::collections::BTreeMap;
+
+use crate::errors::TackleError;
+
+use super::{HookRunner, HookState, HookWithState, PROJECT_NAMESPACE};
+
+/// The separator between the package and the hook in a qualified hook ID.
+pub static ID_SEPARATOR: char = ':';
+
+/// The namespaces of the packages in a pipeline, mapped to the URL of the
+/// package which took each, or `None` for the inline hooks of the project.
+#[derive(Default)]
+pub struct Namespaces(BTreeMap<String, Option<String>>);
+
+impl Namespaces {
+    /// Get the namespace of the package installed from `url`, or of the inline
+    /// hooks of the project if `url` is `None`. A package is named `preferred`
+    /// unless the name is reserved for the project or taken by another package.
+    pub fn assign(&mut self, preferred: &str, url: Option<&str>) -> String {
+        let namespace = match url {
+            None => PROJECT_NAMESPACE,
+            Some(url) => match self.0.get(preferred) {
+                _ if preferred == PROJECT_NAMESPACE => url,
+                Some(owner) if owner.as_deref() != Some(url) => url,
+                _ => preferred,
+            },
+        };
+        self.0.insert(namespace.to_owned(), url.map(|url| url.to_owned()));
+        namespace.to_owned()
+    }
+}
+
+/// The hooks a condition refers to, as indices into the pipeline.
+#[derive(Default, Debug, PartialEq)]
+pub struct ConditionReferences {
+    pub successful: Vec<usize>,
+    pub failed: Vec<usize>,
+    pub skipped: Vec<usize>,
+}
+
+impl ConditionReferences {
+    /// Every hook the condition refers to, with the state it requires.
+    pub fn requirements(&self) -> impl Iterator<Item = (usize, HookState)> + '_ {
+        let successful = self.successful.iter().map(|index| (*index, HookState::Successful));
+        let failed = self.failed.iter().map(|index| (*index, HookState::Failed));
+        let skipped = self.skipped.iter().map(|index| (*index, HookState::Skipped));
+        successful.chain(failed).chain(skipped)
+    }
+}
+
+impl HookWithState {
+    /// The ID of the hook qualified by the name of its package.
+    pub fn qualified_id(&self) -> String {
+        format!("{}{}{}", self.namespace, ID_SEPARATOR, self.id)
+    }
+
+    /// Test if a package name or URL refers to the package of this hook.
+    fn is_in_package(&self, package: &str) -> bool {
+        self.namespace == package || self.url.as_deref() == Some(package)
+    }
+}
+
+impl HookRunner {
+    /// Find the hooks a reference made by the hook at index `from` may refer
+    /// to. Exactly one candidate is returned for valid references.
+    fn find_references(&self, from: usize, reference: &str) -> Vec<usize> {
+        let matching = |filter: &dyn Fn(&HookWithState) -> bool| -> Vec<usize> {
+            let hooks = self.hooks.iter().enumerate();
+            hooks.filter(|(_, hook)| filter(hook)).map(|(index, _)| index).collect()
+        };
+        if let Some((package, id)) = reference.rsplit_once(ID_SEPARATOR) {
+            return matching(&|hook| hook.id == id && hook.is_in_package(package));
+        }
+        let package = self.hooks[from].package;
+        let local = matching(&|hook| hook.id == reference && hook.package == package);
+        match local.is_empty() {
+            true => matching(&|hook| hook.id == reference),
+            false => local,
+        }
+    }
+
+    /// Resolve a reference made by the hook at index `from`.
+    fn resolve_reference(&self, from: usize, reference: &str) -> Result<usize, TackleError> {
+        let hook = self.hooks[from].qualified_id();
+        match self.find_references(from, reference).as_slice() {
+            [index] => Ok(*index),
+            [] => Err(TackleError::UnknownHookReference { hook, reference: reference.to_owned() }),
+            candidates => {
+                let candidates: Vec<_> = candidates
+                    .iter()
+                    .map(|index| format!("'{}'", self.hooks[*index].qualified_id()))
+                    .collect();
+                Err(TackleError::AmbiguousHookReference {
+                    hook,
+                    reference: reference.to_owned(),
+                    candidates: candidates.join(", "),
+                })
+            }
+        }
+    }
+
+    /// Resolve the hook IDs referred to by the conditions of every hook,
+    /// failing on references which match no hook or more than one.
+    pub fn resolve_references(&mut self) -> Result<(), TackleError> {
+        for from in 0..self.hooks.len() {
+            let resolve = |ids: &[String]| -> Result<Vec<usize>, TackleError> {
+                ids.iter().map(|id| self.resolve_reference(from, id)).collect()
+            };
+            let references = self.hooks[from]
+                .hook
+                .conditions
+                .iter()
+                .map(|condition| {
+                    Ok(ConditionReferences {
+                        successful: resolve(&condition.successful)?,
+                        failed: resolve(&condition.failed)?,
+                        skipped: resolve(&condition.skipped)?,
+                    })
+                })
+                .collect::<Result<_, TackleError>>()?;
+            self.hooks[from].references = references;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::TackleError,
+        hooks::{Hook, HookRunner, HookSource, HookState},
+        package::{hook_ids, options::OptionValues, Package},
+        project::EffectiveHook,
+    };
+
+    #[test]
+    fn test_resolve_references() {
+        let package = |toml: &str| -> Vec<EffectiveHook> {
+            let package: Package = toml::from_str(toml).unwrap();
+            let definitions = package.hooks.into_hook(Hook::PreCommit);
+            definitions
+                .into_iter()
+                .map(|definition| EffectiveHook { definition, active: true, overridden: vec![] })
+                .collect()
+        };
+        let lint = r#"
+            [[hooks.pre-commit]]
+            id = "lint"
+            command = ["true"]
+
+            [[hooks.pre-commit]]
+            command = ["./hooks/report.sh"]
+            conditions = [{ successful = ["lint"] }]
+            "#;
+        let style = r#"
+            [[hooks.pre-commit]]
+            id = "lint"
+            command = ["true"]
+
+            [[hooks.pre-commit]]
+            id = "summary"
+            command = ["true"]
+            conditions = [{ successful = ["lint", "acme/lint:lint"] }]
+            "#;
+        let definitions: Vec<_> =
+            package(lint).iter().map(|hook| hook.definition.clone()).collect();
+        assert_eq!(hook_ids(&definitions), vec!["lint", "report"]);
+
+        let load = |project: &str| -> Result<HookRunner, TackleError> {
+            let mut runner = HookRunner::default();
+            let source = |namespace, url| HookSource { namespace, url, directory: None };
+            let options = OptionValues::new();
+            runner.add_hooks(source("lint", Some("acme/lint")), &options, package(lint));
+            runner.add_hooks(source("style", Some("acme/style")), &options, package(style));
+            runner.add_hooks(source("project", None), &options, package(project));
+            runner.resolve_references()?;
+            Ok(runner)
+        };
+        let hook = |conditions: &str| {
+            format!("[[hooks.pre-commit]]\ncommand = [\"true\"]\nconditions = [{}]", conditions)
+        };
+
+        // bare references prefer the hook of the same package, or else the only
+        // hook with the ID
+        let mut runner = load(&hook("{ successful = [\"report\", \"style:lint\"] }")).unwrap();
+        assert_eq!(runner.hooks[1].references[0].successful, vec![0]);
+        assert_eq!(runner.hooks[3].references[0].successful, vec![2, 0]);
+        assert_eq!(runner.hooks[4].references[0].successful, vec![1, 2]);
+        assert_eq!(runner.hooks[4].qualified_id(), "project:true");
+        runner.set_hook_state("style:summary", HookState::Skipped);
+        assert_eq!(runner.hooks[3].state, HookState::Skipped);
+
+        let ambiguous = load(&hook("{ failed = [\"lint\"] }"));
+        assert!(matches!(ambiguous, Err(TackleError::AmbiguousHookReference { .. })));
+        let unknown = load(&hook("{ skipped = [\"style:format\"] }"));
+        assert!(matches!(unknown, Err(TackleError::UnknownHookReference { .. })));
+
+        // packages named like the project or another package are named by URL
+        let mut runner = HookRunner::default();
+        let source = |namespace, url| HookSource { namespace, url, directory: None };
+        let options = OptionValues::new();
+        runner.add_hooks(source("lint", Some("acme/lint")), &options, package(lint));
+        runner.add_hooks(source("lint", Some("corp/lint")), &options, package(lint));
+        runner.add_hooks(source("project", Some("acme/project")), &options, package(lint));
+        let project = hook("{ successful = [\"lint:lint\", \"acme/project:report\"] }");
+        runner.add_hooks(source("project", None), &options, package(&project));
+        runner.resolve_references().unwrap();
+        assert_eq!(runner.hooks[2].qualified_id(), "corp/lint:lint");
+        assert_eq!(runner.hooks[5].qualified_id(), "acme/project:report");
+        assert_eq!(runner.hooks[6].qualified_id(), "project:true");
+        assert_eq!(runner.hooks[6].references[0].successful, vec![0, 5]);
+    }
+}
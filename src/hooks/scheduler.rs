@@ -0,0 +1,229 @@
+//! Schedules the hooks of a pipeline, running the hooks which do not depend
+//! on each other in parallel.
+use std::{
+    io::{self, ErrorKind, Write},
+    process::{Command, Output, Stdio},
+    sync::mpsc,
+    thread,
+};
+
+use log::{debug, error, info, warn};
+
+use crate::errors::TackleError;
+
+use super::{HookRunner, HookState, HookWithState};
+
+/// Spawn a hook command and wait for it to exit. When `buffer` is set, the
+/// output of the command is captured rather than written to the terminal.
+fn spawn_hook(mut command: Command, stdin: Option<&[u8]>, buffer: bool) -> io::Result<Output> {
+    if stdin.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    if buffer {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    debug!("Spawning {:?}", command);
+    let mut child = command.spawn()?;
+    // every hook receives its own copy of the input git passed to Tackle
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        match pipe.write_all(input) {
+            // the hook exited without reading its input
+            Err(err) if err.kind() == ErrorKind::BrokenPipe => {}
+            result => result?,
+        }
+    }
+    child.wait_with_output()
+}
+
+impl HookWithState {
+    /// Write the buffered output of a finished hook, and get its final state.
+    fn finish(&self, result: io::Result<Output>) -> HookState {
+        let name = self.qualified_id();
+        match result {
+            Ok(output) => {
+                // the output of a hook is written at once, so that the output
+                // of parallel hooks does not interleave
+                let _ = io::stdout().write_all(&output.stdout);
+                let _ = io::stderr().write_all(&output.stderr);
+                match output.status.success() {
+                    true => HookState::Successful,
+                    false => {
+                        error!("Hook '{}' failed with {}", name, output.status);
+                        HookState::Failed
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Failed to run hook '{}': {}", name, err);
+                HookState::Failed
+            }
+        }
+    }
+}
+
+impl HookRunner {
+    /// Run the pipeline to completion, passing `args` and `stdin` to every hook
+    /// command. The references between hooks are resolved and checked before
+    /// any hook runs.
+    ///
+    /// Every hook which is ready runs at once, up to `jobs` hooks at a time,
+    /// with its output buffered when more than one job is allowed. Serial hooks
+    /// wait for the running hooks to finish, letting the ready hooks after them
+    /// start in the meantime, and run on their own. Pending
+    /// hooks whose referenced hooks have finished without matching their
+    /// conditions are skipped, and hooks still pending at the end are blocked.
+    ///
+    /// Only blocking hooks which failed make the run fail. Blocked hooks never
+    /// ran, so like skipped hooks they are reported with a warning but do not
+    /// abort the git operation, even if they are blocking.
+    pub fn run(
+        &mut self,
+        args: &[String],
+        stdin: Option<&[u8]>,
+        jobs: usize,
+    ) -> Result<(), TackleError> {
+        self.resolve_references()?;
+        self.check_graph()?;
+        let jobs = jobs.max(1);
+        let buffer = jobs > 1;
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            let mut running = 0;
+            // whether a serial hook is running
+            let mut exclusive = false;
+            loop {
+                while running < jobs && !exclusive {
+                    // serial hooks wait for the running hooks to finish, while
+                    // the ready hooks after them take the free slots
+                    let index = match self.next_hook_index(running > 0) {
+                        Some(index) => index,
+                        None => break,
+                    };
+                    let hook = &self.hooks[index];
+                    let name = hook.qualified_id();
+                    let command = match hook.command(args) {
+                        Some(command) => command,
+                        None => {
+                            warn!("Hook '{}' has an empty command", name);
+                            self.hooks[index].state = HookState::Failed;
+                            continue;
+                        }
+                    };
+                    info!("Running hook '{}'...", name);
+                    exclusive = hook.hook.serial;
+                    self.hooks[index].state = HookState::Running;
+                    running += 1;
+                    let sender = sender.clone();
+                    scope.spawn(move || {
+                        let _ = sender.send((index, spawn_hook(command, stdin, buffer)));
+                    });
+                }
+                // the conditions of these hooks can no longer match
+                let decided = self.hooks.iter().position(|hook| {
+                    hook.state == HookState::Pending
+                        && self.has_finished_references(hook)
+                        && !self.is_matching_hook(hook)
+                });
+                if let Some(index) = decided {
+                    info!("Skipping hook '{}'", self.hooks[index].qualified_id());
+                    self.hooks[index].state = HookState::Skipped;
+                    continue;
+                }
+                if running == 0 {
+                    break;
+                }
+                let (index, result) = receiver.recv().expect("a hook thread disconnected");
+                running -= 1;
+                exclusive = false;
+                self.hooks[index].state = self.hooks[index].finish(result);
+            }
+        });
+        for hook in &mut self.hooks {
+            if hook.state == HookState::Pending {
+                let reason = "the hooks it refers to never finished".to_owned();
+                warn!("Hook '{}' is blocked: {}", hook.qualified_id(), reason);
+                hook.state = HookState::Blocked(reason);
+            }
+        }
+        // abort if a blocking hook failed, hooks which never ran do not count
+        match self.hooks.iter().find(|hook| hook.state == HookState::Failed && hook.hook.blocking) {
+            Some(hook) => Err(TackleError::BlockingHookFailed(hook.qualified_id())),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        hooks::{Hook, HookRunner, HookState},
+        package::Package,
+    };
+
+    #[test]
+    fn test_run_in_parallel() {
+        let temp = tempfile::tempdir().unwrap();
+        let log = temp.path().join("log");
+        let args = [log.to_string_lossy().into_owned()];
+        // every hook records when it starts and ends in the log it is passed,
+        // optionally waiting for another hook to start in between
+        let hook = |id: &str, waits_for: Option<&str>, serial: bool| {
+            let wait = waits_for.map_or_else(String::new, |other| {
+                format!(
+                    "for i in $(seq 500); do grep -q \"start {}\" \"$1\" && break; sleep 0.02; \
+                     done; ",
+                    other
+                )
+            });
+            format!(
+                "[[hooks.pre-commit]]\nid = \"{0}\"\nserial = {1}\n\
+                 command = [\"sh\", \"-c\", 'echo start {0} >> \"$1\"; {2}echo end {0} >> \"$1\"', \
+                 \"{0}\"]\n",
+                id, serial, wait
+            )
+        };
+        let report = "conditions = [{ successful = [\"a\", \"b\"] }]\n";
+        let run = |toml: String| -> Vec<String> {
+            let _ = fs::remove_file(&log);
+            let package: Package = toml::from_str(&toml).unwrap();
+            let mut runner =
+                HookRunner::from_hooks(package.hooks.into_hook(Hook::PreCommit)).unwrap();
+            assert!(runner.run(&args, None, 2).is_ok());
+            let states: Vec<_> = runner.hooks.iter().map(|hook| &hook.state).collect();
+            assert_eq!(states, vec![&HookState::Successful; 3]);
+            fs::read_to_string(&log).unwrap().lines().map(|line| line.to_owned()).collect()
+        };
+
+        // both hooks start before either of them ends
+        let lines = run(hook("a", Some("b"), false)
+            + &hook("b", Some("a"), false)
+            + &hook("report", None, false)
+            + report);
+        let mut starts = lines[..2].to_vec();
+        starts.sort();
+        let mut ends = lines[2..4].to_vec();
+        ends.sort();
+        assert_eq!(starts, vec!["start a", "start b"]);
+        assert_eq!(ends, vec!["end a", "end b"]);
+        assert_eq!(lines[4..], ["start report", "end report"]);
+
+        // serial hooks run on their own
+        let lines = run(hook("a", None, false)
+            + &hook("b", None, true)
+            + &hook("report", None, false)
+            + report);
+        let expected = ["start a", "end a", "start b", "end b", "start report", "end report"];
+        assert_eq!(lines, expected);
+
+        // hooks after a waiting serial hook run in parallel before it
+        let lines = run(hook("a", Some("b"), false)
+            + &hook("serial", None, true)
+            + &hook("b", Some("a"), false));
+        let mut starts = lines[..2].to_vec();
+        starts.sort();
+        assert_eq!(starts, vec!["start a", "start b"]);
+        assert_eq!(lines[4..], ["start serial", "end serial"]);
+    }
+}
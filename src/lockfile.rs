@@ -0,0 +1,240 @@
+//! Contains types and methods for interacting with the project lockfile.
+use std::{collections::BTreeSet, fs, path::Path};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::TackleError,
+    package::{source::GitSource, FetchedPackage},
+    project::TackleManifest,
+    util::is_local_package,
+};
+
+/// The current lockfile format version.
+static LOCKFILE_VERSION: &str = "1";
+
+/// A package pinned by the lockfile.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct LockedPackage {
+    /// The package URL, as written in the manifest.
+    pub url: String,
+    /// The canonical URL of the repository containing the package.
+    pub repository: String,
+    /// The path from the repository root to the package directory.
+    pub path: String,
+    /// The name of the tag or branch the commit was resolved from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The SHA of the commit the package was installed at.
+    pub commit: String,
+    /// The content hash of the package directory.
+    pub integrity: String,
+    /// The URLs of the packages this package depends on.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+}
+
+impl LockedPackage {
+    /// Create a locked package for the package at `url`.
+    pub fn new(url: &str, fetched: &FetchedPackage) -> Result<LockedPackage, TackleError> {
+        let source = GitSource::parse(url)?;
+        Ok(LockedPackage {
+            url: url.to_owned(),
+            repository: source.key(),
+            path: source.path,
+            version: fetched.version.clone(),
+            commit: fetched.commit.clone(),
+            integrity: fetched.integrity.clone(),
+            dependencies: fetched.package.dependencies.keys().cloned().collect(),
+        })
+    }
+}
+
+/// The lockfile, pinning every installed package to an exact commit.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TackleLockfile {
+    /// The lockfile version.
+    pub version: String,
+    /// The locked packages, sorted by URL.
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Default for TackleLockfile {
+    fn default() -> Self {
+        TackleLockfile { version: LOCKFILE_VERSION.to_owned(), packages: Vec::new() }
+    }
+}
+
+impl TackleLockfile {
+    /// Get the locked package with the given URL.
+    pub fn get(&self, url: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|package| package.url == url)
+    }
+
+    /// Lock a package, replacing any existing entry for it.
+    pub fn insert(&mut self, package: LockedPackage) {
+        self.remove(&package.url);
+        self.packages.push(package);
+        self.packages.sort_by(|a, b| a.url.cmp(&b.url));
+    }
+
+    /// Remove a package from the lockfile.
+    pub fn remove(&mut self, url: &str) {
+        self.packages.retain(|package| package.url != url);
+    }
+
+    /// Find the URLs of packages where the manifest and the lockfile disagree.
+    pub fn diff(&self, manifest: &TackleManifest) -> Vec<String> {
+        let mut changed: Vec<String> = Vec::new();
+        // packages inside the project are not locked
+        for entry in manifest.hooks.packages().filter(|entry| !is_local_package(&entry.url)) {
+            let matches = self.get(&entry.url).is_some_and(|locked| {
+                locked.commit == entry.commit && locked.integrity == entry.integrity
+            });
+            if !matches && !changed.contains(&entry.url) {
+                changed.push(entry.url.clone());
+            }
+        }
+        // every dependency must be locked, and every locked package must be needed
+        let reachable = self.reachable(manifest);
+        for package in &self.packages {
+            for dependency in &package.dependencies {
+                let locked = is_local_package(dependency) || self.get(dependency).is_some();
+                if !locked && !changed.contains(dependency) {
+                    changed.push(dependency.clone());
+                }
+            }
+            if !reachable.contains(&package.url) && !changed.contains(&package.url) {
+                changed.push(package.url.clone());
+            }
+        }
+        changed
+    }
+
+    /// Find the URLs of the packages in the manifest and their locked dependencies.
+    fn reachable(&self, manifest: &TackleManifest) -> BTreeSet<String> {
+        let mut reachable = BTreeSet::new();
+        let mut pending: Vec<String> = manifest.hooks.package_urls().into_iter().collect();
+        while let Some(url) = pending.pop() {
+            if let Some(package) = self.get(&url) {
+                pending.extend(
+                    package.dependencies.iter().filter(|url| !reachable.contains(*url)).cloned(),
+                );
+            }
+            reachable.insert(url);
+        }
+        reachable
+    }
+
+    /// Remove the packages which are neither in the manifest nor a dependency
+    /// of a package in it, returning them.
+    pub fn prune(&mut self, manifest: &TackleManifest) -> Vec<LockedPackage> {
+        let reachable = self.reachable(manifest);
+        let (kept, pruned) =
+            self.packages.drain(..).partition(|package| reachable.contains(&package.url));
+        self.packages = kept;
+        pruned
+    }
+}
+
+/// Read the lockfile, returning an empty lockfile if it does not exist.
+pub fn read_lockfile<P: AsRef<Path>>(workdir: P) -> Result<TackleLockfile, TackleError> {
+    let path = workdir.as_ref().join(".tackle/tackle.lock");
+    if !path.exists() {
+        debug!("No lockfile found at '{}'", path.display());
+        return Ok(TackleLockfile::default());
+    }
+    let contents = fs::read_to_string(&path).map_err(|_| TackleError::LockfileReadFailed)?;
+    let lockfile: TackleLockfile =
+        toml::from_str(&contents).map_err(TackleError::LockfileParseFailed)?;
+    Ok(lockfile)
+}
+
+/// Write the lockfile.
+pub fn write_lockfile<P: AsRef<Path>>(
+    workdir: P,
+    lockfile: &TackleLockfile,
+) -> Result<(), TackleError> {
+    let path = workdir.as_ref().join(".tackle/tackle.lock");
+    let contents = format!(
+        "# This file is generated by Tackle. Do not edit it by hand.\n{}",
+        toml::to_string(lockfile).unwrap()
+    );
+    fs::write(&path, contents).map_err(|_err| TackleError::LockfileWriteFailed)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{read_lockfile, LockedPackage, TackleLockfile};
+    use crate::{
+        errors::TackleError,
+        hooks::Hook,
+        project::{TackleManifest, TackleManifestHook, TackleManifestHooks},
+    };
+
+    #[test]
+    fn test_lockfile_diff() {
+        let mut manifest = TackleManifest {
+            version: "1".to_owned(),
+            settings: Default::default(),
+            hooks: TackleManifestHooks::default(),
+        };
+        let entry = TackleManifestHook {
+            url: "acme/lint".to_owned(),
+            version: "*".to_owned(),
+            commit: "abc".to_owned(),
+            integrity: "sha256-0".to_owned(),
+            only: Vec::new(),
+            skip: Vec::new(),
+            overrides: Default::default(),
+            options: Default::default(),
+        };
+        manifest.hooks.insert_package(&[Hook::PreCommit, Hook::CommitMsg], entry);
+
+        let mut lockfile = TackleLockfile::default();
+        assert_eq!(lockfile.diff(&manifest), vec!["acme/lint".to_owned()]);
+
+        let mut locked = LockedPackage {
+            url: "acme/lint".to_owned(),
+            repository: "github.com/acme/lint".to_owned(),
+            path: ".".to_owned(),
+            version: None,
+            commit: "abc".to_owned(),
+            integrity: "sha256-0".to_owned(),
+            dependencies: Vec::new(),
+        };
+        lockfile.insert(locked.clone());
+        assert!(lockfile.diff(&manifest).is_empty());
+
+        locked.url = "acme/fmt".to_owned();
+        lockfile.insert(locked.clone());
+        assert_eq!(lockfile.diff(&manifest), vec!["acme/fmt".to_owned()]);
+
+        // dependencies of locked packages must be locked too
+        locked.url = "acme/lint".to_owned();
+        locked.dependencies = vec!["acme/fmt".to_owned(), "acme/toolchain".to_owned()];
+        lockfile.insert(locked);
+        assert_eq!(lockfile.diff(&manifest), vec!["acme/toolchain".to_owned()]);
+
+        manifest.hooks.remove_package("acme/lint");
+        let pruned: Vec<_> =
+            lockfile.prune(&manifest).into_iter().map(|package| package.url).collect();
+        assert_eq!(pruned, vec!["acme/fmt".to_owned(), "acme/lint".to_owned()]);
+        assert!(lockfile.packages.is_empty());
+    }
+
+    #[test]
+    fn test_read_invalid_lockfile() {
+        let workdir = tempfile::tempdir().unwrap();
+        fs::create_dir(workdir.path().join(".tackle")).unwrap();
+        fs::write(workdir.path().join(".tackle/tackle.lock"), "packages = 1\n").unwrap();
+        let err = read_lockfile(workdir.path()).err().unwrap();
+        assert!(matches!(err, TackleError::LockfileParseFailed(_)));
+        assert!(err.to_string().starts_with("Failed to parse the lockfile: "));
+    }
+}
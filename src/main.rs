@@ -3,8 +3,11 @@ use cli::run_cli;
 mod cli;
 mod errors;
 mod hooks;
+mod lockfile;
 mod package;
 mod project;
+#[cfg(test)]
+mod testing;
 mod util;
 
 fn main() {
@@ -1,72 +1,161 @@
 //! Handles caching of hook packages.
+//!
+//! The cache is shared between every project on the machine. It holds one bare
+//! mirror per remote repository under `repositories/`, and one exported tree per
+//! commit under `store/`, keyed by the commit SHA. Projects link to the exported
+//! trees rather than cloning their own copies.
+//!
+//! The cache lives in `~/.tackle`, unless the `TACKLE_HOME` environment
+//! variable points elsewhere.
 
 use std::{fs, path::PathBuf, sync::Mutex};
 
+use git2::{build::CheckoutBuilder, Oid, Repository};
 use lazy_static::lazy_static;
 use log::debug;
 
 use crate::{
     errors::TackleError,
-    package::Package,
-    util::{extract_package_path, resolve_package_url},
+    package::{source::GitSource, Package},
 };
 
+/// The environment variable overriding the location of the cache directory.
+pub static CACHE_DIR_ENV: &str = "TACKLE_HOME";
+
 lazy_static! {
-    /// The path to the project root. This is cached to avoid repeated calls to `get_project_root`.
+    /// The path to the cache directory. This is cached to avoid repeated calls to `resolve_cache_directory`.
     pub static ref CACHE_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
 }
 
 /// Resolve the location of the tackle cache directory.
 /// If the directory does not exist, it will be created.
 pub fn resolve_cache_directory() -> Result<PathBuf, TackleError> {
+    let mut cached = CACHE_DIR.lock().unwrap();
     // check if the cache directory was already resolved
-    if let Some(cache_dir) = CACHE_DIR.lock().unwrap().as_ref() {
+    if let Some(cache_dir) = cached.as_ref() {
         debug!("Using cached directory: {}", cache_dir.display());
         return Ok(cache_dir.clone());
     }
     debug!("Resolving the location of the cache directory...");
-    let mut cache_dir = dirs::home_dir().unwrap();
-    cache_dir.push(".tackle");
+    let cache_dir = match std::env::var_os(CACHE_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::home_dir().ok_or(TackleError::CacheDirectoryNotFound)?.join(".tackle"),
+    };
     // check if the directory exists, and create it if it doesn't
     if !cache_dir.exists() {
         debug!("Creating the cache directory...");
-        fs::create_dir_all(&cache_dir)?;
+        fs::create_dir_all(&cache_dir)
+            .map_err(|source| TackleError::CacheWriteFailed { path: cache_dir.clone(), source })?;
     }
+    *cached = Some(cache_dir.clone());
     Ok(cache_dir)
 }
 
-/// Lookup the location of the repository for a particular package.
-pub fn lookup_repository<S: AsRef<str>>(url: S) -> Result<Option<PathBuf>, TackleError> {
-    let cache_dir = resolve_cache_directory()?;
-    let mut path = cache_dir;
-    path.push(url.as_ref());
-    // resolve the package url
-    let path: PathBuf = resolve_package_url(path.to_str().unwrap())?.into();
+/// Get the path of the bare mirror of the repository containing a package.
+pub fn repository_path(source: &GitSource) -> Result<PathBuf, TackleError> {
+    Ok(resolve_cache_directory()?.join("repositories").join(format!("{}.git", source.key())))
+}
+
+/// Get the path of the exported tree of a commit.
+pub fn store_path(commit: &str) -> Result<PathBuf, TackleError> {
+    Ok(resolve_cache_directory()?.join("store").join(commit))
+}
 
+/// Lookup the location of the repository for a particular package.
+pub fn lookup_repository(source: &GitSource) -> Result<Option<PathBuf>, TackleError> {
+    let path = repository_path(source)?;
     if path.exists() {
-        debug!("Found package at '{}'", path.display());
+        debug!("Found repository at '{}'", path.display());
         Ok(Some(path))
     } else {
-        debug!("Package not found at '{}'", path.display());
+        debug!("Repository not found at '{}'", path.display());
         Ok(None)
     }
 }
 
-pub fn lookup_package<S: AsRef<str>>(url: S) -> Result<Option<Package>, TackleError> {
-    // lookup the repository the package is in
-    let package_dir = lookup_repository(&url)?;
-    if let None = package_dir {
-        return Ok(None);
-    }
-    let mut package_dir = package_dir.unwrap();
-    // get the package name
-    let url = extract_package_path(&url)?;
-    package_dir.push(url);
+/// Lookup a package exported at the given commit.
+pub fn lookup_package(source: &GitSource, commit: &str) -> Result<Option<Package>, TackleError> {
+    let package_dir = store_path(commit)?.join(&source.path);
     // test if the package directory exists and read its manifest
-    if package_dir.exists() && package_dir.is_dir() {
+    if package_dir.join("package.toml").is_file() {
         let package = Package::from_path(&package_dir)?;
         Ok(Some(package))
     } else {
         Ok(None)
     }
 }
+
+/// Export the tree of a commit into the store, unless it was already exported.
+pub fn materialize_commit(repository: &Repository, commit: &str) -> Result<PathBuf, TackleError> {
+    let path = store_path(commit)?;
+    if path.exists() {
+        debug!("Commit '{}' already exported to '{}'", commit, path.display());
+        return Ok(path);
+    }
+    debug!("Exporting commit '{}' to '{}'...", commit, path.display());
+    let tree = Oid::from_str(commit)
+        .and_then(|oid| repository.find_commit(oid))
+        .and_then(|commit| commit.tree())
+        .map_err(|_| TackleError::CommitNotFound(commit.to_owned()))?;
+    // export into a temporary directory first so that an interrupted export
+    // never leaves a partial tree in the store
+    let staging = path.with_extension(format!("tmp-{}", std::process::id()));
+    let write_failed = |source| TackleError::CacheWriteFailed { path: staging.clone(), source };
+    fs::create_dir_all(&staging).map_err(write_failed)?;
+    if repository
+        .checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().target_dir(&staging).force()))
+        .is_err()
+    {
+        // do not leave the partial export behind
+        fs::remove_dir_all(&staging).map_err(write_failed)?;
+        return Err(TackleError::CommitNotFound(commit.to_owned()));
+    }
+    if fs::rename(&staging, &path).is_err() {
+        // another process exported the same commit concurrently
+        fs::remove_dir_all(&staging).map_err(write_failed)?;
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{materialize_commit, store_path};
+    use crate::{
+        errors::TackleError,
+        testing::{commit_files, init_origin, use_test_cache},
+    };
+
+    #[test]
+    fn test_materialize_commit() {
+        use_test_cache();
+        let (origin, repository, _) = init_origin(".");
+        let commit = commit_files(&repository, &[("hooks/lint.sh", "exit 0\n")]);
+        let path = materialize_commit(&repository, &commit).unwrap();
+        assert_eq!(path, store_path(&commit).unwrap());
+        assert_eq!(fs::read_to_string(path.join("hooks/lint.sh")).unwrap(), "exit 0\n");
+        // exporting again reuses the tree in the store
+        assert_eq!(materialize_commit(&repository, &commit).unwrap(), path);
+
+        // a commit whose blobs are missing fails without leaving anything behind
+        let broken = commit_files(&repository, &[("hooks/fmt.sh", "exit 1\n")]);
+        let blob = repository
+            .revparse_single(&format!("{}:hooks/fmt.sh", broken))
+            .unwrap()
+            .id()
+            .to_string();
+        let (dir, file) = blob.split_at(2);
+        fs::remove_file(origin.path().join(".git/objects").join(dir).join(file)).unwrap();
+        let err = materialize_commit(&repository, &broken).err().unwrap();
+        assert!(matches!(err, TackleError::CommitNotFound(commit) if commit == broken));
+        let store = store_path(&broken).unwrap();
+        let leftovers = fs::read_dir(store.parent().unwrap())
+            .unwrap()
+            .filter(|entry| {
+                entry.as_ref().unwrap().file_name().to_string_lossy().starts_with(&broken)
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+}
@@ -1 +1,196 @@
 //! This module contains the code for fetching packages.
+use std::path::Path;
+
+use git2::{build::RepoBuilder, Object, Repository};
+use log::debug;
+
+use super::resolve::select_tag;
+use crate::{errors::TackleError, util::PackageRevision};
+
+/// Open the bare mirror at `path`, cloning it from `url` if it does not exist.
+pub fn open_or_clone<P: AsRef<Path>>(url: &str, path: P) -> Result<Repository, TackleError> {
+    let path = path.as_ref();
+    if path.exists() {
+        debug!("Opening existing mirror at '{}'...", path.display());
+        let repository =
+            Repository::open_bare(path).map_err(|_| TackleError::RepositoryFetchFailed)?;
+        // the same repository may be requested over another protocol
+        repository.remote_set_url("origin", url).map_err(|_| TackleError::RepositoryFetchFailed)?;
+        return Ok(repository);
+    }
+    debug!("Cloning '{}' into '{}'...", url, path.display());
+    RepoBuilder::new().bare(true).clone(url, path).map_err(|err| {
+        debug!("Clone failed: {}", err);
+        TackleError::RepositoryCloneFailed
+    })
+}
+
+/// Fetch the given refspecs from `origin`.
+fn fetch(repository: &Repository, refspecs: &[&str]) -> Result<(), TackleError> {
+    debug!("Fetching {:?} from origin...", refspecs);
+    repository
+        .find_remote("origin")
+        .and_then(|mut remote| remote.fetch(refspecs, None, None))
+        .map_err(|err| {
+            debug!("Fetch failed: {}", err);
+            TackleError::RepositoryFetchFailed
+        })
+}
+
+/// Fetch every tag from `origin` and get their names.
+pub fn fetch_tags(repository: &Repository) -> Result<Vec<String>, TackleError> {
+    fetch(repository, &["+refs/tags/*:refs/tags/*"])?;
+    local_tags(repository)
+}
+
+/// Get the names of the tags already present in the repository.
+pub fn local_tags(repository: &Repository) -> Result<Vec<String>, TackleError> {
+    let tags = repository.tag_names(None).map_err(|_| TackleError::RepositoryFetchFailed)?;
+    Ok(tags.iter().flatten().map(|tag| tag.to_owned()).collect())
+}
+
+/// Fetch and find the object a revision points to, returning it together with
+/// a label describing the chosen tag or branch. When `offline` is set, only
+/// the objects already in the repository are used.
+fn find_revision<'r>(
+    repository: &'r Repository,
+    revision: Option<&PackageRevision>,
+    offline: bool,
+) -> Result<(Object<'r>, Option<String>), TackleError> {
+    match revision {
+        None => {
+            if !offline {
+                fetch(repository, &["HEAD"])?;
+            }
+            // fall back to the head recorded by the initial clone
+            let target = repository
+                .revparse_single("FETCH_HEAD")
+                .or_else(|_| repository.revparse_single("HEAD"))
+                .map_err(|_| TackleError::RepositoryFetchFailed)?;
+            Ok((target, None))
+        }
+        Some(PackageRevision::Branch(branch)) => {
+            if !offline {
+                let refspec = format!("+refs/heads/{0}:refs/remotes/origin/{0}", branch);
+                fetch(repository, &[&refspec])?;
+            }
+            let target = repository
+                .revparse_single(&format!("refs/remotes/origin/{}", branch))
+                .map_err(|_| TackleError::BranchNotFound(branch.clone()))?;
+            Ok((target, Some(branch.clone())))
+        }
+        Some(PackageRevision::Version(version)) => {
+            let tags = if offline { local_tags(repository)? } else { fetch_tags(repository)? };
+            let tag = select_tag(&tags, version)?;
+            let target = repository
+                .revparse_single(&format!("refs/tags/{}", tag))
+                .map_err(|_| TackleError::NoMatchingVersion(version.clone()))?;
+            Ok((target, Some(tag)))
+        }
+        Some(PackageRevision::Commit(commit)) => {
+            // only fetch when the revision is not available locally
+            if let Some(found) = find_named_revision(repository, commit) {
+                return Ok(found);
+            }
+            if let Ok(target) = repository.revparse_single(commit) {
+                return Ok((target, None));
+            }
+            if offline {
+                return Err(TackleError::CommitNotFound(commit.clone()));
+            }
+            debug!("Commit '{}' not found locally, fetching...", commit);
+            fetch(
+                repository,
+                &["+refs/heads/*:refs/remotes/origin/*", "+refs/tags/*:refs/tags/*"],
+            )?;
+            if let Some(found) = find_named_revision(repository, commit) {
+                return Ok(found);
+            }
+            let target = repository
+                .revparse_single(commit)
+                .map_err(|_| TackleError::CommitNotFound(commit.clone()))?;
+            Ok((target, None))
+        }
+    }
+}
+
+/// Find a tag or a branch with the given name. Revisions which look like a
+/// commit SHA, such as numeric tags, name a tag or a branch if one exists.
+fn find_named_revision<'r>(
+    repository: &'r Repository,
+    name: &str,
+) -> Option<(Object<'r>, Option<String>)> {
+    ["refs/tags", "refs/remotes/origin"].iter().find_map(|prefix| {
+        let target = repository.revparse_single(&format!("{}/{}", prefix, name)).ok()?;
+        Some((target, Some(name.to_owned())))
+    })
+}
+
+/// Fetch a revision of a repository, returning the SHA of the commit it points
+/// to and the name of the chosen tag or branch. The head of the default branch
+/// is used if no revision is given. Nothing is fetched when `offline` is set.
+pub fn resolve_revision(
+    repository: &Repository,
+    revision: Option<&PackageRevision>,
+    offline: bool,
+) -> Result<(String, Option<String>), TackleError> {
+    let (target, label) = find_revision(repository, revision, offline)?;
+    let commit = target.peel_to_commit().map_err(|_| TackleError::RepositoryFetchFailed)?;
+    Ok((commit.id().to_string(), label))
+}
+
+/// Get the one-line summaries of the commits reachable from `to` but not from `from`.
+pub fn commit_log(
+    repository: &Repository,
+    from: &str,
+    to: &str,
+) -> Result<Vec<String>, TackleError> {
+    let log = || -> Result<Vec<String>, git2::Error> {
+        let mut revwalk = repository.revwalk()?;
+        revwalk.push(git2::Oid::from_str(to)?)?;
+        // the old commit may be unrelated to the new one, e.g. after a force push
+        if let Ok(from) = git2::Oid::from_str(from) {
+            let _ = revwalk.hide(from);
+        }
+        revwalk
+            .map(|oid| {
+                let commit = repository.find_commit(oid?)?;
+                Ok(format!("{:.7} {}", commit.id(), commit.summary().unwrap_or_default()))
+            })
+            .collect()
+    };
+    log().map_err(|_| TackleError::CommitNotFound(to.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use git2::{Repository, Signature};
+
+    use super::resolve_revision;
+    use crate::util::PackageRevision;
+
+    #[test]
+    fn test_resolve_numeric_tag() {
+        let temp = tempfile::tempdir().unwrap();
+        let repository = Repository::init(temp.path()).unwrap();
+        let signature = Signature::now("tackle", "tackle@example.com").unwrap();
+        let tree = repository.find_tree(repository.index().unwrap().write_tree().unwrap()).unwrap();
+        let first = repository.commit(Some("HEAD"), &signature, &signature, "one", &tree, &[]);
+        let first = repository.find_commit(first.unwrap()).unwrap();
+        let second = repository
+            .commit(Some("HEAD"), &signature, &signature, "two", &tree, &[&first])
+            .unwrap()
+            .to_string();
+        repository.tag_lightweight("20240101", first.as_object(), false).unwrap();
+
+        // numeric tags look like abbreviated commits, but name the tag
+        let revision = PackageRevision::parse("20240101").unwrap();
+        assert_eq!(revision, PackageRevision::Commit("20240101".to_owned()));
+        let resolved = resolve_revision(&repository, Some(&revision), true).unwrap();
+        assert_eq!(resolved, (first.id().to_string(), Some("20240101".to_owned())));
+
+        let revision = PackageRevision::parse(&second[..7]).unwrap();
+        let resolved = resolve_revision(&repository, Some(&revision), true).unwrap();
+        assert_eq!(resolved, (second, None));
+    }
+}
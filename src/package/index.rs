@@ -0,0 +1,204 @@
+//! Package indexes, which are git repositories holding a catalogue that maps
+//! package names to their source URLs.
+//!
+//! The catalogue is an `index.toml` or `index.json` file at the root of the
+//! index package, with one entry per package:
+//!
+//! ```toml
+//! [packages.eslint]
+//! url = "skyezerfox/hooks/eslint"
+//! description = "Lint staged JavaScript files with ESLint"
+//! tags = ["javascript", "lint"]
+//! hooks = ["pre-commit"]
+//! ```
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use git2::{Oid, Repository};
+use log::{debug, warn};
+use serde::{Deserialize, Deserializer};
+
+use super::{
+    cache::lookup_repository, download::resolve_revision, open_package_repository,
+    source::GitSource,
+};
+use crate::{errors::TackleError, hooks::Hook};
+
+/// The catalogue files looked for in an index, in order.
+static INDEX_FILES: [&str; 2] = ["index.toml", "index.json"];
+
+/// A package listed in an index.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct IndexEntry {
+    /// The URL the package is installed from.
+    pub url: String,
+    /// A description of the package.
+    pub description: Option<String>,
+    /// Keywords to search the package by.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The git hooks the package provides. Hooks unknown to this version of
+    /// Tackle are skipped, so that indexes can list newer hooks.
+    #[serde(default, deserialize_with = "deserialize_hooks")]
+    pub hooks: Vec<Hook>,
+}
+
+/// Deserialize the hooks of an index entry, skipping the unknown hooks.
+fn deserialize_hooks<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Hook>, D::Error> {
+    let names = Vec::<String>::deserialize(deserializer)?;
+    let hooks = names.iter().filter_map(|name| match name.parse() {
+        Ok(hook) => Some(hook),
+        Err(err) => {
+            warn!("Skipping hook in package index: {}", err);
+            None
+        }
+    });
+    Ok(hooks.collect())
+}
+
+/// The catalogue of an index.
+#[derive(Debug, Deserialize, Default)]
+pub struct PackageIndex {
+    /// The packages in the index, keyed by name.
+    #[serde(default)]
+    pub packages: BTreeMap<String, IndexEntry>,
+}
+
+impl PackageIndex {
+    /// Parse a catalogue file, choosing the format from its file name.
+    pub fn parse(file: &str, contents: &str) -> Result<PackageIndex, String> {
+        match file.ends_with(".json") {
+            true => serde_json::from_str(contents).map_err(|err| err.to_string()),
+            false => toml::from_str(contents).map_err(|err| err.to_string()),
+        }
+    }
+
+    /// Find the packages whose name, description or tags contain `term`,
+    /// ignoring case.
+    pub fn search(&self, term: &str) -> Vec<(&str, &IndexEntry)> {
+        let term = term.to_lowercase();
+        let matches = |text: &str| text.to_lowercase().contains(&term);
+        self.packages
+            .iter()
+            .filter(|(name, entry)| {
+                matches(name)
+                    || entry.description.as_deref().is_some_and(matches)
+                    || entry.tags.iter().any(|tag| matches(tag))
+            })
+            .map(|(name, entry)| (name.as_str(), entry))
+            .collect()
+    }
+}
+
+/// Test if a package is a bare name to look up in the indexes, rather than a
+/// URL or a path.
+pub fn is_package_name(url: &str) -> bool {
+    !url.is_empty() && !url.contains('/') && !url.contains(':')
+}
+
+/// Refresh an index through the cache and read its catalogue. If the index
+/// cannot be fetched, the cached copy is used instead. When `offline` is set,
+/// only the cached copy is used.
+pub fn read_index(url: &str, offline: bool) -> Result<PackageIndex, TackleError> {
+    let source = GitSource::parse(url)?;
+    let cached = || -> Result<(Repository, String), TackleError> {
+        let path = lookup_repository(&source)?.ok_or_else(|| TackleError::PackageNotCached {
+            package: url.to_owned(),
+            commit: "HEAD".to_owned(),
+        })?;
+        let repository =
+            Repository::open_bare(path).map_err(|_| TackleError::RepositoryFetchFailed)?;
+        let (commit, _) = resolve_revision(&repository, None, true)?;
+        Ok((repository, commit))
+    };
+    let (repository, commit) = match offline {
+        true => cached()?,
+        false => match open_package_repository(url)
+            .and_then(|repository| Ok((resolve_revision(&repository, None, false)?, repository)))
+        {
+            Ok(((commit, _), repository)) => (repository, commit),
+            Err(err) => {
+                warn!("Failed to refresh the index '{}', using the cached copy: {}", url, err);
+                cached()?
+            }
+        },
+    };
+
+    let tree = Oid::from_str(&commit)
+        .and_then(|oid| repository.find_commit(oid))
+        .and_then(|commit| commit.tree())
+        .map_err(|_| TackleError::CommitNotFound(commit.clone()))?;
+    for file in INDEX_FILES {
+        let path = match source.path.as_str() {
+            "." => PathBuf::from(file),
+            directory => Path::new(directory).join(file),
+        };
+        let blob = tree
+            .get_path(&path)
+            .and_then(|entry| entry.to_object(&repository))
+            .and_then(|object| object.peel_to_blob());
+        if let Ok(blob) = blob {
+            debug!("Reading '{}' from index '{}'", file, url);
+            let contents = String::from_utf8_lossy(blob.content());
+            return PackageIndex::parse(file, &contents)
+                .map_err(|err| TackleError::IndexParseFailed(url.to_owned(), err));
+        }
+    }
+    Err(TackleError::IndexParseFailed(url.to_owned(), "no index.toml or index.json".to_owned()))
+}
+
+/// Resolve a bare package name to its URL through the given indexes, using
+/// the first index that lists it.
+pub fn resolve_package_name(
+    indexes: &[String],
+    name: &str,
+    offline: bool,
+) -> Result<String, TackleError> {
+    for index in indexes {
+        if let Some(entry) = read_index(index, offline)?.packages.remove(name) {
+            debug!("Resolved '{}' to '{}' through '{}'", name, entry.url, index);
+            return Ok(entry.url);
+        }
+    }
+    Err(TackleError::PackageNotInIndex(name.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_package_name, PackageIndex};
+    use crate::hooks::Hook;
+
+    #[test]
+    fn test_parse_and_search_index() {
+        let toml = r#"
+            [packages.eslint]
+            url = "skyezerfox/hooks/eslint"
+            description = "Lint JavaScript"
+            tags = ["javascript"]
+            hooks = ["pre-commit"]
+
+            [packages.commitlint]
+            url = "git@git.acme.dev:team/commitlint.git"
+            hooks = ["commit-msg", "post-index-change"]
+        "#;
+        let index = PackageIndex::parse("index.toml", toml).unwrap();
+        assert_eq!(index.packages["eslint"].hooks, vec![Hook::PreCommit]);
+        // hooks unknown to Tackle are skipped
+        assert_eq!(index.packages["commitlint"].hooks, vec![Hook::CommitMsg]);
+
+        let json = r#"{"packages": {"eslint": {"url": "skyezerfox/hooks/eslint"}}}"#;
+        let json = PackageIndex::parse("index.json", json).unwrap();
+        assert_eq!(json.packages["eslint"].url, index.packages["eslint"].url);
+
+        let names = |term| index.search(term).into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(names("lint"), vec!["commitlint", "eslint"]);
+        assert_eq!(names("JAVASCRIPT"), vec!["eslint"]);
+        assert!(names("python").is_empty());
+
+        assert!(is_package_name("eslint"));
+        assert!(!is_package_name("skyezerfox/hooks"));
+        assert!(!is_package_name("git@git.acme.dev:team/hooks.git"));
+    }
+}
@@ -0,0 +1,122 @@
+//! Computes content hashes of package directories.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::errors::TackleError;
+
+/// The prefix of integrity hashes, naming the hash algorithm.
+static INTEGRITY_PREFIX: &str = "sha256-";
+
+/// Recursively collect the files in `dir`, skipping git metadata.
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), TackleError> {
+    let read_failed = |source| TackleError::IntegrityReadFailed { file: dir.to_owned(), source };
+    for entry in fs::read_dir(dir).map_err(read_failed)? {
+        let entry = entry.map_err(read_failed)?;
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type().map_err(read_failed)?.is_dir() {
+            collect_files(root, &path, files)?;
+        } else {
+            files.push(path.strip_prefix(root).unwrap().to_owned());
+        }
+    }
+    Ok(())
+}
+
+/// Test if the file at `path` is executable.
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Compute a deterministic content hash of the package directory at `path`.
+///
+/// Files are hashed in sorted path order together with their mode, so the hash
+/// is stable across machines and independent of git metadata.
+pub fn compute_integrity<P: AsRef<Path>>(path: P) -> Result<String, TackleError> {
+    let root = path.as_ref();
+    let mut files = Vec::new();
+    collect_files(root, root, &mut files)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for file in files {
+        let full_path = root.join(&file);
+        let read_failed =
+            |source| TackleError::IntegrityReadFailed { file: full_path.clone(), source };
+        let metadata = fs::symlink_metadata(&full_path).map_err(read_failed)?;
+        let (mode, contents) = if metadata.file_type().is_symlink() {
+            let target = fs::read_link(&full_path).map_err(read_failed)?;
+            ("120000", target.to_string_lossy().into_owned().into_bytes())
+        } else if is_executable(&metadata) {
+            ("100755", fs::read(&full_path).map_err(read_failed)?)
+        } else {
+            ("100644", fs::read(&full_path).map_err(read_failed)?)
+        };
+        // normalise path separators so hashes match across platforms
+        hasher.update(file.to_string_lossy().replace('\\', "/").as_bytes());
+        hasher.update([0]);
+        hasher.update(mode.as_bytes());
+        hasher.update([0]);
+        hasher.update((contents.len() as u64).to_le_bytes());
+        hasher.update(&contents);
+    }
+    Ok(format!("{}{}", INTEGRITY_PREFIX, hex::encode(hasher.finalize())))
+}
+
+/// Verify that the package directory at `path` matches the expected hash.
+pub fn verify_integrity<P: AsRef<Path>>(
+    path: P,
+    package: &str,
+    expected: &str,
+) -> Result<(), TackleError> {
+    let actual = compute_integrity(path)?;
+    if actual != expected {
+        return Err(TackleError::IntegrityMismatch {
+            package: package.to_owned(),
+            expected: expected.to_owned(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{compute_integrity, verify_integrity};
+
+    #[test]
+    fn test_compute_integrity() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::create_dir_all(dir.join("scripts")).unwrap();
+        fs::write(dir.join("package.toml"), "[hooks]\n").unwrap();
+        fs::write(dir.join("scripts/lint.sh"), "exit 0\n").unwrap();
+        let integrity = compute_integrity(dir).unwrap();
+        assert!(integrity.starts_with("sha256-"));
+
+        // git metadata is ignored
+        fs::write(dir.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        assert_eq!(compute_integrity(dir).unwrap(), integrity);
+
+        // contents are not
+        fs::write(dir.join("scripts/lint.sh"), "exit 1\n").unwrap();
+        assert_ne!(compute_integrity(dir).unwrap(), integrity);
+        assert!(verify_integrity(dir, "acme/lint", &integrity).is_err());
+    }
+}
@@ -1 +1,271 @@
 //! This module contains the logic for linking packages to their projects.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use git2::Repository;
+use log::{debug, info, warn};
+
+use super::{resolve_package_directory, Package};
+use crate::{errors::TackleError, hooks::Hook, project::TackleManifest, util::is_local_package};
+
+/// The marker identifying hook files generated by Tackle.
+static SHIM_MARKER: &str = "# Generated by Tackle.";
+/// The extension appended to foreign hooks replaced by a shim.
+static BACKUP_EXTENSION: &str = "pre-tackle";
+/// The start of the shim line falling back to the executable which generated it.
+static FALLBACK_PREFIX: &str = "command -v \"$TACKLE\"";
+
+/// The link state of a git hook.
+#[derive(Debug, PartialEq)]
+pub enum LinkState {
+    /// The hook is used and its shim is up to date.
+    Linked,
+    /// The hook is used, but its shim was generated by a different version of Tackle.
+    Outdated,
+    /// The hook is used, but no shim exists.
+    Missing,
+    /// The hook file was not generated by Tackle.
+    Foreign,
+    /// A shim exists, but no package uses the hook.
+    Unused,
+    /// The hook is neither used nor linked.
+    Unlinked,
+}
+
+/// Resolve the directory git reads hooks from, respecting `core.hooksPath`.
+pub fn resolve_hooks_directory<P: AsRef<Path>>(workdir: P) -> Result<PathBuf, TackleError> {
+    let repo = Repository::open(&workdir).map_err(|_| TackleError::RepositoryDiscoveryFailed)?;
+    if let Ok(hooks_path) = repo.config().and_then(|config| config.get_path("core.hooksPath")) {
+        debug!("Using core.hooksPath: {}", hooks_path.display());
+        return Ok(workdir.as_ref().join(hooks_path));
+    }
+    // worktrees share the hooks directory of the main repository
+    let git_dir = repo.path().to_owned();
+    let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => git_dir.join(common_dir.trim()),
+        Err(_) => git_dir,
+    };
+    Ok(common_dir.join("hooks"))
+}
+
+/// Generate the contents of the shim for a hook.
+fn generate_shim(hook: Hook) -> String {
+    let fallback = std::env::current_exe()
+        .map(|exe| exe.display().to_string())
+        .unwrap_or_else(|_| "tackle".to_owned());
+    format!(
+        "#!/bin/sh\n\
+         {marker} Do not edit, run `tackle link` to regenerate.\n\
+         TACKLE=tackle\n\
+         {fallback_prefix} >/dev/null 2>&1 || TACKLE=\"{fallback}\"\n\
+         exec \"$TACKLE\" run {hook} -- \"$@\"\n",
+        marker = SHIM_MARKER,
+        fallback_prefix = FALLBACK_PREFIX,
+        fallback = fallback,
+        hook = hook.git_name(),
+    )
+}
+
+/// Get the lines of a shim, without the fallback executable, which differs
+/// between the machines and builds of Tackle the shim may be generated by.
+fn shim_lines(shim: &str) -> Vec<&str> {
+    shim.lines().filter(|line| !line.starts_with(FALLBACK_PREFIX)).collect()
+}
+
+/// Test if the file at `path` is a shim generated by Tackle.
+fn is_shim(path: &Path) -> bool {
+    fs::read_to_string(path).map(|contents| contents.contains(SHIM_MARKER)).unwrap_or(false)
+}
+
+/// Get the path foreign hooks are backed up to.
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension(BACKUP_EXTENSION)
+}
+
+/// Get the link state of a hook.
+pub fn link_state<P: AsRef<Path>>(hooks_dir: P, hook: Hook, used: bool) -> LinkState {
+    let path = hooks_dir.as_ref().join(hook.git_name());
+    match (path.exists(), is_shim(&path), used) {
+        (false, _, true) => LinkState::Missing,
+        (false, _, false) => LinkState::Unlinked,
+        (true, false, _) => LinkState::Foreign,
+        (true, true, false) => LinkState::Unused,
+        (true, true, true) => match fs::read_to_string(&path) {
+            Ok(contents) if shim_lines(&contents) == shim_lines(&generate_shim(hook)) => {
+                LinkState::Linked
+            }
+            _ => LinkState::Outdated,
+        },
+    }
+}
+
+/// Write the shim for a hook, backing up any foreign hook it replaces.
+fn link_hook(hooks_dir: &Path, hook: Hook) -> Result<(), TackleError> {
+    let path = hooks_dir.join(hook.git_name());
+    if path.exists() && !is_shim(&path) {
+        let backup = backup_path(&path);
+        if backup.exists() {
+            return Err(TackleError::HookBackupExists(backup));
+        }
+        warn!("Backing up existing '{}' hook to '{}'", hook, backup.display());
+        fs::rename(&path, &backup)
+            .map_err(|source| TackleError::HookLinkFailed { path: path.clone(), source })?;
+    }
+    debug!("Writing shim to '{}'", path.display());
+    fs::write(&path, generate_shim(hook))
+        .map_err(|source| TackleError::HookLinkFailed { path: path.clone(), source })?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .map_err(|source| TackleError::HookLinkFailed { path, source })?;
+    }
+    Ok(())
+}
+
+/// Remove the shim for a hook, restoring any foreign hook it replaced.
+fn unlink_hook(hooks_dir: &Path, hook: Hook) -> Result<(), TackleError> {
+    let path = hooks_dir.join(hook.git_name());
+    if !is_shim(&path) {
+        return Ok(());
+    }
+    debug!("Removing shim at '{}'", path.display());
+    fs::remove_file(&path)
+        .map_err(|source| TackleError::HookLinkFailed { path: path.clone(), source })?;
+    let backup = backup_path(&path);
+    if backup.exists() {
+        info!("Restoring backed up '{}' hook", hook);
+        fs::rename(&backup, &path)
+            .map_err(|source| TackleError::HookLinkFailed { path, source })?;
+    }
+    Ok(())
+}
+
+/// Point the project directory at `link` to the exported package tree at
+/// `target`, replacing whatever was linked before.
+pub fn link_package_directory(target: &Path, link: &Path) -> Result<(), TackleError> {
+    unlink_package_directory(link)?;
+    if let Some(parent) = link.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|source| TackleError::PackageLinkFailed { path: link.to_owned(), source })?;
+    }
+    debug!("Linking '{}' to '{}'", link.display(), target.display());
+    #[cfg(unix)]
+    let linked = std::os::unix::fs::symlink(target, link);
+    #[cfg(not(unix))]
+    let linked = copy_directory(target, link);
+    linked.map_err(|source| TackleError::PackageLinkFailed { path: link.to_owned(), source })
+}
+
+/// Remove a project package directory created by `link_package_directory`.
+pub fn unlink_package_directory(link: &Path) -> Result<(), TackleError> {
+    let removed = match fs::symlink_metadata(link) {
+        Ok(metadata) if metadata.file_type().is_symlink() => fs::remove_file(link),
+        Ok(_) => fs::remove_dir_all(link),
+        Err(_) => Ok(()),
+    };
+    removed.map_err(|source| TackleError::PackageLinkFailed { path: link.to_owned(), source })
+}
+
+/// Recursively copy a directory, for platforms where symlinks need elevated permissions.
+#[cfg(not(unix))]
+fn copy_directory(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_directory(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Get the hooks used by the project. Packages inside the project are read in
+/// place, as they may define hooks they were not installed for.
+pub fn used_hooks<P: AsRef<Path>>(workdir: P, manifest: &TackleManifest) -> Vec<Hook> {
+    let mut used = manifest.hooks.used_hooks();
+    for url in manifest.hooks.package_urls().iter().filter(|url| is_local_package(url)) {
+        match resolve_package_directory(&workdir, url, "").and_then(Package::from_path) {
+            Ok(package) => used.extend(
+                package
+                    .hooks
+                    .0
+                    .iter()
+                    .filter(|(_, hooks)| !hooks.is_empty())
+                    .map(|(hook, _)| *hook),
+            ),
+            Err(err) => warn!("Failed to read the hooks of '{}': {}", url, err),
+        }
+    }
+    used.sort();
+    used.dedup();
+    used
+}
+
+/// Synchronise the shims in the hooks directory with the hooks used by the manifest.
+pub fn link_hooks<P: AsRef<Path>>(
+    workdir: P,
+    manifest: &TackleManifest,
+) -> Result<(), TackleError> {
+    let hooks_dir = resolve_hooks_directory(&workdir)?;
+    fs::create_dir_all(&hooks_dir)
+        .map_err(|source| TackleError::HookLinkFailed { path: hooks_dir.clone(), source })?;
+    let used = used_hooks(&workdir, manifest);
+    for hook in Hook::ALL {
+        match link_state(&hooks_dir, hook, used.contains(&hook)) {
+            LinkState::Missing | LinkState::Outdated | LinkState::Foreign
+                if used.contains(&hook) =>
+            {
+                info!("Linking '{}' hook...", hook);
+                link_hook(&hooks_dir, hook)?;
+            }
+            LinkState::Unused => {
+                info!("Unlinking '{}' hook...", hook);
+                unlink_hook(&hooks_dir, hook)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{is_shim, link_hook, link_state, unlink_hook, LinkState};
+    use crate::hooks::Hook;
+
+    #[test]
+    fn test_link_hook() {
+        let temp = tempfile::tempdir().unwrap();
+        let hooks_dir = temp.path();
+        let path = hooks_dir.join("pre-commit");
+        fs::write(&path, "#!/bin/sh\nexit 0\n").unwrap();
+        assert_eq!(link_state(hooks_dir, Hook::PreCommit, true), LinkState::Foreign);
+
+        link_hook(hooks_dir, Hook::PreCommit).unwrap();
+        assert!(is_shim(&path));
+        assert!(hooks_dir.join("pre-commit.pre-tackle").exists());
+        assert_eq!(link_state(hooks_dir, Hook::PreCommit, true), LinkState::Linked);
+        assert_eq!(link_state(hooks_dir, Hook::PreCommit, false), LinkState::Unused);
+        // shims generated by another build of Tackle are still up to date
+        let shim = fs::read_to_string(&path).unwrap();
+        assert!(shim.ends_with("run pre-commit -- \"$@\"\n"));
+        let fallback = shim.lines().find(|line| line.starts_with("command -v")).unwrap();
+        fs::write(&path, shim.replace(fallback, "command -v \"$TACKLE\" || TACKLE=/opt/tackle"))
+            .unwrap();
+        assert_eq!(link_state(hooks_dir, Hook::PreCommit, true), LinkState::Linked);
+        fs::write(&path, shim.replace(" run ", " run --jobs 2 ")).unwrap();
+        assert_eq!(link_state(hooks_dir, Hook::PreCommit, true), LinkState::Outdated);
+
+        unlink_hook(hooks_dir, Hook::PreCommit).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "#!/bin/sh\nexit 0\n");
+        assert_eq!(link_state(hooks_dir, Hook::PostCommit, false), LinkState::Unlinked);
+    }
+}
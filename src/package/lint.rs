@@ -0,0 +1,811 @@
+//! Checks `package.toml` manifests for mistakes, reporting every problem
+//! with its location in the file.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use toml::Spanned;
+
+use super::{generate_hook_ids, options::OptionType};
+use crate::{
+    errors::TackleError,
+    hooks::{reference::ID_SEPARATOR, Hook},
+};
+
+/// The keys allowed at the top of a package manifest.
+static PACKAGE_KEYS: [&str; 6] =
+    ["name", "description", "version", "dependencies", "options", "hooks"];
+
+/// The keys allowed in an option declaration.
+static OPTION_KEYS: [&str; 3] = ["type", "default", "description"];
+
+/// The keys allowed in a hook definition.
+static HOOK_KEYS: [&str; 6] = ["id", "command", "dependencies", "blocking", "serial", "conditions"];
+
+/// The keys allowed in a hook condition.
+static CONDITION_KEYS: [&str; 5] = ["successful", "failed", "skipped", "exists", "branch"];
+
+/// The keys of a condition matching the state of other hooks.
+static STATE_KEYS: [&str; 3] = ["successful", "failed", "skipped"];
+
+/// The key toml uses to deserialize datetimes.
+static DATETIME_KEY: &str = "$__toml_private_datetime";
+
+/// How serious a problem is.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    /// The package will not load, or will not behave as intended.
+    Error,
+    /// The package loads, but is likely not what the author meant.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// A problem found in a package manifest.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    /// The manifest file containing the problem.
+    pub file: PathBuf,
+    /// The line of the problem, starting at 1.
+    pub line: usize,
+    /// The column of the problem, starting at 1.
+    pub column: usize,
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.file.display(),
+            self.line,
+            self.column,
+            self.severity,
+            self.message
+        )
+    }
+}
+
+/// A TOML value together with its location in the file.
+struct Node {
+    span: (usize, usize),
+    value: Value,
+}
+
+/// A TOML value whose keys and children keep their location in the file.
+enum Value {
+    Table(Vec<(Spanned<String>, Node)>),
+    Array(Vec<Node>),
+    String(String),
+    Integer,
+    Float,
+    Boolean,
+    Datetime,
+}
+
+impl Value {
+    /// Describe the type of the value for a diagnostic.
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Table(_) => "a table",
+            Value::Array(_) => "an array",
+            Value::String(_) => "a string",
+            Value::Integer => "an integer",
+            Value::Float => "a float",
+            Value::Boolean => "a boolean",
+            Value::Datetime => "a datetime",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let spanned = Spanned::<Value>::deserialize(deserializer)?;
+        Ok(Node { span: (spanned.start(), spanned.end()), value: spanned.into_inner() })
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ValueVisitor;
+
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = Value;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a TOML value")
+            }
+
+            fn visit_bool<E: de::Error>(self, _: bool) -> Result<Value, E> {
+                Ok(Value::Boolean)
+            }
+
+            fn visit_i64<E: de::Error>(self, _: i64) -> Result<Value, E> {
+                Ok(Value::Integer)
+            }
+
+            fn visit_u64<E: de::Error>(self, _: u64) -> Result<Value, E> {
+                Ok(Value::Integer)
+            }
+
+            fn visit_f64<E: de::Error>(self, _: f64) -> Result<Value, E> {
+                Ok(Value::Float)
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Value, E> {
+                Ok(Value::String(value.to_owned()))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+                let mut nodes = Vec::new();
+                while let Some(node) = seq.next_element()? {
+                    nodes.push(node);
+                }
+                Ok(Value::Array(nodes))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(key) = map.next_key::<Spanned<String>>()? {
+                    if key.get_ref() == DATETIME_KEY {
+                        map.next_value::<String>()?;
+                        return Ok(Value::Datetime);
+                    }
+                    entries.push((key, map.next_value()?));
+                }
+                Ok(Value::Table(entries))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// Collects the diagnostics of a manifest.
+struct Linter<'a> {
+    file: &'a Path,
+    contents: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    /// Report a problem at a byte offset into the manifest.
+    fn report(&mut self, offset: usize, severity: Severity, message: String) {
+        let (line, column) = line_column(self.contents, offset);
+        self.diagnostics.push(Diagnostic {
+            file: self.file.to_owned(),
+            line,
+            column,
+            severity,
+            message,
+        });
+    }
+
+    /// Report the keys of a table which are not in `allowed`, suggesting the
+    /// closest allowed key.
+    fn check_keys(&mut self, entries: &[(Spanned<String>, Node)], allowed: &[&str], context: &str) {
+        for (key, _) in entries {
+            if allowed.contains(&key.get_ref().as_str()) {
+                continue;
+            }
+            let message = match suggest(key.get_ref(), allowed.iter().copied()) {
+                Some(suggestion) => format!(
+                    "unknown key '{}' in {}, did you mean '{}'?",
+                    key.get_ref(),
+                    context,
+                    suggestion
+                ),
+                None => format!("unknown key '{}' in {}", key.get_ref(), context),
+            };
+            self.report(key.start(), Severity::Error, message);
+        }
+    }
+
+    /// Get the offset of a table. Tables in an array of tables have no span
+    /// of their own, so their `[[...]]` header is used instead.
+    fn table_offset(&self, node: &Node, entries: &[(Spanned<String>, Node)]) -> usize {
+        if self.contents[node.span.0..].starts_with('{') {
+            return node.span.0;
+        }
+        let first = entries.first().map_or(node.span.0, |(key, _)| key.start());
+        self.contents[..first].rfind("[[").unwrap_or(first)
+    }
+
+    /// Check that a value is a string, returning it.
+    fn expect_string<'n>(&mut self, node: &'n Node, name: &str) -> Option<&'n str> {
+        match &node.value {
+            Value::String(value) => Some(value),
+            value => {
+                let message = format!("expected a string for '{}', found {}", name, value.kind());
+                self.report(node.span.0, Severity::Error, message);
+                None
+            }
+        }
+    }
+
+    /// Check that a value is an array of strings, returning the strings with
+    /// their offsets.
+    fn expect_strings<'n>(&mut self, node: &'n Node, name: &str) -> Vec<(usize, &'n str)> {
+        let items = match &node.value {
+            Value::Array(items) => items,
+            value => {
+                let message =
+                    format!("expected an array of strings for '{}', found {}", name, value.kind());
+                self.report(node.span.0, Severity::Error, message);
+                return Vec::new();
+            }
+        };
+        items
+            .iter()
+            .filter_map(|item| Some((item.span.0, self.expect_string(item, name)?)))
+            .collect()
+    }
+
+    /// Check the whole manifest.
+    fn check_package(&mut self, root: &Node) {
+        let entries = match &root.value {
+            Value::Table(entries) => entries,
+            _ => return,
+        };
+        self.check_keys(entries, &PACKAGE_KEYS, "the package");
+        for (key, node) in entries {
+            match key.get_ref().as_str() {
+                "name" | "description" | "version" => {
+                    self.expect_string(node, key.get_ref());
+                }
+                "dependencies" => self.check_dependencies(node),
+                "options" => self.check_options(node),
+                "hooks" => self.check_hooks(node),
+                _ => {}
+            }
+        }
+    }
+
+    /// Check the `[dependencies]` table.
+    fn check_dependencies(&mut self, node: &Node) {
+        let entries = match &node.value {
+            Value::Table(entries) => entries,
+            value => {
+                let message =
+                    format!("expected a table for 'dependencies', found {}", value.kind());
+                return self.report(node.span.0, Severity::Error, message);
+            }
+        };
+        for (url, revision) in entries {
+            self.expect_string(revision, url.get_ref());
+        }
+    }
+
+    /// Check the `[options]` table, and that the default of every option has
+    /// the declared type.
+    fn check_options(&mut self, node: &Node) {
+        let entries = match &node.value {
+            Value::Table(entries) => entries,
+            value => {
+                let message = format!("expected a table for 'options', found {}", value.kind());
+                return self.report(node.span.0, Severity::Error, message);
+            }
+        };
+        for (name, option) in entries {
+            let fields = match &option.value {
+                Value::Table(fields) => fields,
+                value => {
+                    let message = format!(
+                        "expected a table declaring option '{}', found {}",
+                        name.get_ref(),
+                        value.kind()
+                    );
+                    self.report(option.span.0, Severity::Error, message);
+                    continue;
+                }
+            };
+            self.check_keys(fields, &OPTION_KEYS, "an option");
+            let field = |key: &str| fields.iter().find(|(name, _)| name.get_ref() == key);
+            if let Some((_, node)) = field("description") {
+                self.expect_string(node, "description");
+            }
+            let kind = match field("type") {
+                Some((_, node)) => match self.expect_string(node, "type") {
+                    Some(kind) if OptionType::NAMES.contains(&kind) => kind,
+                    Some(kind) => {
+                        let message = match suggest(kind, OptionType::NAMES.into_iter()) {
+                            Some(suggestion) => format!(
+                                "unknown option type '{}', did you mean '{}'?",
+                                kind, suggestion
+                            ),
+                            None => format!(
+                                "unknown option type '{}', expected one of: {}",
+                                kind,
+                                OptionType::NAMES.join(", ")
+                            ),
+                        };
+                        self.report(node.span.0, Severity::Error, message);
+                        continue;
+                    }
+                    None => continue,
+                },
+                None => {
+                    let message = format!("option '{}' is missing 'type'", name.get_ref());
+                    self.report(name.start(), Severity::Error, message);
+                    continue;
+                }
+            };
+            if let Some((_, default)) = field("default") {
+                let matches = match (kind, &default.value) {
+                    ("string", Value::String(_))
+                    | ("int", Value::Integer)
+                    | ("bool", Value::Boolean) => true,
+                    ("list", Value::Array(items)) => {
+                        items.iter().all(|item| matches!(item.value, Value::String(_)))
+                    }
+                    _ => false,
+                };
+                if !matches {
+                    let message = format!(
+                        "the default of option '{}' is {}, but its type is '{}'",
+                        name.get_ref(),
+                        default.value.kind(),
+                        kind
+                    );
+                    self.report(default.span.0, Severity::Error, message);
+                }
+            }
+        }
+    }
+
+    /// Check the `[hooks]` table and every hook definition in it.
+    fn check_hooks(&mut self, node: &Node) {
+        let entries = match &node.value {
+            Value::Table(entries) => entries,
+            value => {
+                let message = format!("expected a table for 'hooks', found {}", value.kind());
+                return self.report(node.span.0, Severity::Error, message);
+            }
+        };
+        for (key, definitions) in entries {
+            if key.get_ref().parse::<Hook>().is_err() {
+                let names = Hook::ALL.map(|hook| hook.git_name());
+                let message = match suggest(key.get_ref(), names.into_iter()) {
+                    Some(suggestion) => format!(
+                        "unknown git hook '{}', did you mean '{}'?",
+                        key.get_ref(),
+                        suggestion
+                    ),
+                    None => format!("unknown git hook '{}'", key.get_ref()),
+                };
+                self.report(key.start(), Severity::Error, message);
+                continue;
+            }
+            let definitions = match &definitions.value {
+                Value::Array(definitions) => definitions,
+                value => {
+                    let message = format!(
+                        "expected an array of hooks for '{}', found {}",
+                        key.get_ref(),
+                        value.kind()
+                    );
+                    self.report(definitions.span.0, Severity::Error, message);
+                    continue;
+                }
+            };
+            self.check_definitions(key.get_ref(), definitions);
+        }
+    }
+
+    /// Check the hook definitions of a git hook, and the references between them.
+    fn check_definitions(&mut self, hook: &str, definitions: &[Node]) {
+        let mut ids: BTreeMap<&str, usize> = BTreeMap::new();
+        // the ID and program of every hook, to generate the IDs of hooks without one
+        let mut hooks = Vec::new();
+        let mut references = Vec::new();
+        for definition in definitions {
+            let entries = match &definition.value {
+                Value::Table(entries) => entries,
+                value => {
+                    let message =
+                        format!("expected a hook definition in '{}', found {}", hook, value.kind());
+                    self.report(definition.span.0, Severity::Error, message);
+                    continue;
+                }
+            };
+            self.check_keys(entries, &HOOK_KEYS, "a hook definition");
+            let field = |name: &str| entries.iter().find(|(key, _)| key.get_ref() == name);
+            let mut hook_id = None;
+            let mut program = None;
+
+            match field("id") {
+                Some((_, node)) => {
+                    hook_id = self.expect_string(node, "id");
+                    if let Some(id) = hook_id {
+                        if id.contains(ID_SEPARATOR) {
+                            let message = format!(
+                                "hook id '{}' cannot contain '{}', which separates the package \
+                                 from the hook in qualified IDs",
+                                id, ID_SEPARATOR
+                            );
+                            self.report(node.span.0, Severity::Error, message);
+                        }
+                        if let Some(first) = ids.insert(id, node.span.0) {
+                            let (line, _) = line_column(self.contents, first);
+                            let message = format!(
+                                "duplicate hook id '{}' in '{}', first defined on line {}",
+                                id, hook, line
+                            );
+                            self.report(node.span.0, Severity::Error, message);
+                        }
+                    }
+                }
+                None => {
+                    let message = format!(
+                        "hook in '{}' has no 'id', so it is given one generated from its command",
+                        hook
+                    );
+                    let offset = self.table_offset(definition, entries);
+                    self.report(offset, Severity::Warning, message);
+                }
+            }
+
+            let declared: Vec<&str> = match field("dependencies") {
+                Some((_, node)) => self
+                    .expect_strings(node, "dependencies")
+                    .into_iter()
+                    .map(|(_, dep)| dep)
+                    .collect(),
+                None => Vec::new(),
+            };
+            match field("command") {
+                Some((_, node)) => {
+                    let command = self.expect_strings(node, "command");
+                    program = command.first().map(|(_, program)| *program);
+                    match command.first() {
+                        None if matches!(node.value, Value::Array(_)) => {
+                            let message = "'command' is empty".to_owned();
+                            self.report(node.span.0, Severity::Error, message);
+                        }
+                        // scripts inside the package are always available
+                        Some((offset, program))
+                            if !program.contains('/') && !declared.contains(program) =>
+                        {
+                            let message = format!(
+                                "'{}' is not declared in the 'dependencies' of the hook",
+                                program
+                            );
+                            self.report(*offset, Severity::Warning, message);
+                        }
+                        _ => {}
+                    }
+                }
+                None => {
+                    let offset = self.table_offset(definition, entries);
+                    let message = "hook definition is missing 'command'".to_owned();
+                    self.report(offset, Severity::Error, message);
+                }
+            }
+
+            for name in ["blocking", "serial"] {
+                if let Some((_, node)) = field(name) {
+                    if !matches!(node.value, Value::Boolean) {
+                        let message = format!(
+                            "expected a boolean for '{}', found {}",
+                            name,
+                            node.value.kind()
+                        );
+                        self.report(node.span.0, Severity::Error, message);
+                    }
+                }
+            }
+            if let Some((_, node)) = field("conditions") {
+                references.extend(self.check_conditions(node));
+            }
+            hooks.push((hook_id, program));
+        }
+
+        // qualified references may refer to the hooks of other packages
+        let known = generate_hook_ids(&hooks);
+        for (offset, id) in references {
+            if !id.contains(ID_SEPARATOR) && !known.iter().any(|known| known == id) {
+                let message = match suggest(id, known.iter().map(String::as_str)) {
+                    Some(suggestion) => format!(
+                        "condition refers to unknown hook '{}' in '{}', did you mean '{}'?",
+                        id, hook, suggestion
+                    ),
+                    None => format!("condition refers to unknown hook '{}' in '{}'", id, hook),
+                };
+                self.report(offset, Severity::Warning, message);
+            }
+        }
+    }
+
+    /// Check the conditions of a hook, returning the hook IDs they refer to
+    /// with their offsets.
+    fn check_conditions<'n>(&mut self, node: &'n Node) -> Vec<(usize, &'n str)> {
+        let conditions = match &node.value {
+            Value::Array(conditions) => conditions,
+            value => {
+                let message = format!(
+                    "expected an array of conditions for 'conditions', found {}",
+                    value.kind()
+                );
+                self.report(node.span.0, Severity::Error, message);
+                return Vec::new();
+            }
+        };
+        let mut references = Vec::new();
+        for condition in conditions {
+            let entries = match &condition.value {
+                Value::Table(entries) => entries,
+                value => {
+                    let message = format!("expected a condition, found {}", value.kind());
+                    self.report(condition.span.0, Severity::Error, message);
+                    continue;
+                }
+            };
+            self.check_keys(entries, &CONDITION_KEYS, "a condition");
+            // a hook ends in exactly one state
+            let mut states: BTreeMap<&str, &str> = BTreeMap::new();
+            for (key, node) in entries {
+                let ids = self.expect_strings(node, key.get_ref());
+                if !STATE_KEYS.contains(&key.get_ref().as_str()) {
+                    continue;
+                }
+                let mut seen = BTreeSet::new();
+                for (offset, id) in ids {
+                    match states.insert(id, key.get_ref()) {
+                        Some(other) if other != key.get_ref() => {
+                            let message = format!(
+                                "condition can never match, '{}' is in both '{}' and '{}'",
+                                id,
+                                other,
+                                key.get_ref()
+                            );
+                            self.report(offset, Severity::Error, message);
+                        }
+                        _ => {}
+                    }
+                    if seen.insert(id) {
+                        references.push((offset, id));
+                    }
+                }
+            }
+        }
+        references
+    }
+}
+
+/// Convert a byte offset into a 1-based line and column.
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    (line, column)
+}
+
+/// Find the candidate closest to a misspelt name, if any is close enough.
+fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= 2.max(candidate.len() / 3))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous + usize::from(a != *b);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Lint the contents of a package manifest, reporting diagnostics against `file`.
+pub fn lint_manifest(file: &Path, contents: &str) -> Vec<Diagnostic> {
+    let mut linter = Linter { file, contents, diagnostics: Vec::new() };
+    match toml::from_str::<Node>(contents) {
+        Ok(root) => linter.check_package(&root),
+        Err(err) => {
+            let (line, column) = err.line_col().map_or((1, 1), |(line, col)| (line + 1, col + 1));
+            linter.diagnostics.push(Diagnostic {
+                file: file.to_owned(),
+                line,
+                column,
+                severity: Severity::Error,
+                message: parse_error_message(&err),
+            });
+        }
+    }
+    linter.diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+    linter.diagnostics
+}
+
+/// Lint the `package.toml` of the package directory at `path`.
+pub fn lint_package<P: AsRef<Path>>(path: P) -> Result<Vec<Diagnostic>, TackleError> {
+    let file = path.as_ref().join("package.toml");
+    let contents = fs::read_to_string(&file)
+        .map_err(|source| TackleError::PackageReadFailed { file: file.clone(), source })?;
+    Ok(lint_manifest(&file, &contents))
+}
+
+/// Get the message of a TOML error without the location toml appends to it.
+pub fn parse_error_message(err: &toml::de::Error) -> String {
+    let message = err.to_string();
+    match message.rsplit_once(" at line ") {
+        Some((message, _)) => message.to_owned(),
+        None => message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde::{
+        de::{self, Visitor},
+        Deserialize, Deserializer,
+    };
+
+    use super::{
+        edit_distance, lint_manifest, Severity, CONDITION_KEYS, HOOK_KEYS, OPTION_KEYS,
+        PACKAGE_KEYS,
+    };
+    use crate::package::{options::PackageOption, HookCondition, HookDefinition, Package};
+
+    /// A deserializer which records the fields a struct deserializes from.
+    struct FieldRecorder<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for FieldRecorder<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("expected a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("recorded the fields"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+            byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map enum
+            identifier ignored_any
+        }
+    }
+
+    /// Get the keys a struct is deserialized from.
+    fn struct_keys<T: for<'de> Deserialize<'de>>() -> &'static [&'static str] {
+        let mut fields: &'static [&'static str] = &[];
+        let _ = T::deserialize(FieldRecorder(&mut fields));
+        fields
+    }
+
+    #[test]
+    fn test_keys_match_schema() {
+        assert_eq!(PACKAGE_KEYS, struct_keys::<Package>());
+        assert_eq!(OPTION_KEYS, struct_keys::<PackageOption>());
+        assert_eq!(HOOK_KEYS, struct_keys::<HookDefinition>());
+        assert_eq!(CONDITION_KEYS, struct_keys::<HookCondition>());
+    }
+
+    #[test]
+    fn test_lint_manifest() {
+        let contents = r#"name = "lint"
+descripton = "Linting hooks"
+
+[dependencies]
+"acme/node" = "^1"
+
+[options]
+max-line = { type = "int", default = "100" }
+
+[hooks]
+pre-comit = []
+
+[[hooks.pre-commit]]
+id = "fmt"
+command = ["./fmt.sh"]
+
+[[hooks.pre-commit]]
+id = "fmt"
+command = []
+
+[[hooks.pre-commit]]
+command = ["eslint", "."]
+dependencies = ["eslint"]
+conditions = [{ successful = ["fmt", "lnt"], failed = ["fmt"] }]
+"#;
+        let diagnostics = lint_manifest(Path::new("package.toml"), contents);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.severity))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (2, 1, Severity::Error),
+                (8, 38, Severity::Error),
+                (11, 1, Severity::Error),
+                (18, 6, Severity::Error),
+                (19, 11, Severity::Error),
+                (21, 1, Severity::Warning),
+                (24, 38, Severity::Warning),
+                (24, 56, Severity::Error),
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "package.toml:2:1: error: unknown key 'descripton' in the package, did you mean \
+             'description'?"
+        );
+        assert!(diagnostics[1].message.contains("is a string, but its type is 'int'"));
+        assert!(diagnostics[2].message.ends_with("did you mean 'pre-commit'?"));
+
+        let diagnostics = lint_manifest(Path::new("package.toml"), "name = \"lint\"\nhooks = [");
+        assert_eq!((diagnostics[0].line, diagnostics[0].severity), (2, Severity::Error));
+
+        // qualified references are not checked, and IDs cannot be qualified
+        let contents = "[[hooks.pre-commit]]\nid = \"acme:fmt\"\ncommand = [\"./fmt.sh\"]\n\
+                        conditions = [{ successful = [\"node:install\"] }]";
+        let diagnostics = lint_manifest(Path::new("package.toml"), contents);
+        let found: Vec<_> =
+            diagnostics.iter().map(|diagnostic| (diagnostic.line, diagnostic.severity)).collect();
+        assert_eq!(found, vec![(2, Severity::Error)]);
+
+        assert_eq!(edit_distance("comand", "command"), 1);
+        assert_eq!(edit_distance("", "id"), 2);
+    }
+
+    #[test]
+    fn test_lint_generated_ids() {
+        // hooks without an ID can be referred to by the ID generated for them
+        let contents = r#"[[hooks.pre-commit]]
+command = ["./scripts/lint.sh"]
+
+[[hooks.pre-commit]]
+command = ["./scripts/lint.sh", "--fix"]
+
+[[hooks.pre-commit]]
+id = "report"
+command = ["./report.sh"]
+conditions = [{ successful = ["lint", "lint-2"], failed = ["lnt-2"] }]
+"#;
+        let diagnostics = lint_manifest(Path::new("package.toml"), contents);
+        let found: Vec<_> =
+            diagnostics.iter().map(|diagnostic| (diagnostic.line, diagnostic.severity)).collect();
+        assert_eq!(
+            found,
+            vec![(1, Severity::Warning), (4, Severity::Warning), (10, Severity::Warning)]
+        );
+        assert!(diagnostics[2].message.ends_with("did you mean 'lint-2'?"));
+    }
+}
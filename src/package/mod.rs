@@ -1,16 +1,35 @@
 //! Contains definitions for interacting with a Tackle package.
 pub mod cache;
 pub mod download;
+pub mod index;
+pub mod integrity;
 pub mod link;
+pub mod lint;
+pub mod options;
 pub mod resolve;
+pub mod scaffold;
+pub mod source;
 
-use std::{fs, path::Path};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
-use git2::Repository;
-use log::debug;
-use serde::Deserialize;
+use git2::{Oid, Repository, Tree, TreeWalkMode, TreeWalkResult};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
 
-use crate::{errors::TackleError, project::get_project_root, util::package_into_git_url};
+use self::{
+    cache::{lookup_package, lookup_repository, materialize_commit, repository_path, store_path},
+    download::{open_or_clone, resolve_revision},
+    integrity::compute_integrity,
+    link::{link_package_directory, unlink_package_directory},
+    lint::parse_error_message,
+    options::PackageOption,
+    source::{GitSource, PackageSource},
+};
+use crate::{errors::TackleError, hooks::Hook, lockfile::TackleLockfile, util::PackageRevision};
 
 /// A `tackle.toml` file defining a hook package.
 #[derive(Deserialize)]
@@ -21,98 +40,592 @@ pub struct Package {
     pub description: Option<String>,
     /// The version of the package.
     pub version: Option<String>,
+    /// Other Tackle packages this package depends on, mapped to the requested
+    /// revision of each.
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+    /// Options which projects can set to configure the hooks, keyed by name.
+    #[serde(default)]
+    pub options: BTreeMap<String, PackageOption>,
     /// Hooks defined by this package.
+    #[serde(default)]
     pub hooks: HookDefinitions,
 }
 
 impl Package {
+    /// Read the package manifest from the package directory at `path`.
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Package, TackleError> {
-        let contents = fs::read_to_string(path)?;
-        let package: Package = toml::from_str(&contents)?;
-        Ok(package)
+        let file = path.as_ref().join("package.toml");
+        let contents = fs::read_to_string(&file)
+            .map_err(|source| TackleError::PackageReadFailed { file: file.clone(), source })?;
+        Package::parse(file, &contents)
+    }
+
+    /// Parse the contents of the package manifest `file`.
+    fn parse(file: PathBuf, contents: &str) -> Result<Package, TackleError> {
+        toml::from_str(contents).map_err(|err| {
+            let (line, column) = err.line_col().map_or((1, 1), |(line, col)| (line + 1, col + 1));
+            TackleError::PackageParseFailed {
+                file,
+                line,
+                column,
+                message: parse_error_message(&err),
+            }
+        })
+    }
+
+    /// The namespace of the hooks of the package in qualified hook IDs: the
+    /// name of the package, or the last segment of the URL it is installed from.
+    pub fn namespace(&self, url: &str) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let url = url.trim_end_matches('/');
+        let url = url.strip_suffix(".git").unwrap_or(url);
+        url.rsplit(['/', ':']).next().unwrap_or(url).to_owned()
     }
 }
 
-/// A collection of hooks defined by a package.
-#[derive(Deserialize)]
-pub struct HookDefinitions {
-    /// A list of hook definitions for the pre-commit hook.
-    #[serde(default = "Vec::new")]
-    pub precommit: Vec<HookDefinition>,
-    /// A list of hook definitions for the post-commit hook.
-    #[serde(default = "Vec::new")]
-    pub postcommit: Vec<HookDefinition>,
+/// A collection of hooks defined by a package, keyed by git hook.
+#[derive(Deserialize, Default)]
+#[serde(transparent)]
+pub struct HookDefinitions(pub BTreeMap<Hook, Vec<HookDefinition>>);
+
+impl HookDefinitions {
+    /// Take the hook definitions for a hook.
+    pub fn into_hook(mut self, hook: Hook) -> Vec<HookDefinition> {
+        self.0.remove(&hook).unwrap_or_default()
+    }
 }
 
-/// A hook definition inside a Tackle package.
-#[derive(Deserialize, PartialEq, Debug)]
+/// A hook definition inside a Tackle package, or inline in the project manifest.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 pub struct HookDefinition {
     /// The ID of the hook. This field is used to identify the hook in
     /// condition blocks of other hooks.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
     /// The command to run.
     pub command: Vec<String>,
     /// OS-level dependencies for the hook.
-    #[serde(default = "Vec::new")]
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     pub dependencies: Vec<String>,
+    /// Whether a failure of this hook should abort the git operation.
+    #[serde(default = "default_blocking", skip_serializing_if = "is_default_blocking")]
+    pub blocking: bool,
+    /// Whether the hook must run on its own, rather than in parallel with
+    /// other hooks.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub serial: bool,
     /// A vector of conditions to test before the hook is run.
-    #[serde(default = "Vec::new")]
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     pub conditions: Vec<HookCondition>,
 }
 
-#[derive(Deserialize, PartialEq, Debug)]
+fn default_blocking() -> bool {
+    true
+}
+
+fn is_default_blocking(blocking: &bool) -> bool {
+    *blocking == default_blocking()
+}
+
+/// Get the IDs of a list of hook definitions. Hooks without an ID are given
+/// one generated from the name of their program, numbered when the name is
+/// already taken, so that it stays the same as long as the hooks keep their order.
+pub fn hook_ids(definitions: &[HookDefinition]) -> Vec<String> {
+    let hooks: Vec<_> = definitions
+        .iter()
+        .map(|definition| {
+            (definition.id.as_deref(), definition.command.first().map(String::as_str))
+        })
+        .collect();
+    generate_hook_ids(&hooks)
+}
+
+/// Get the IDs of hooks given by their optional ID and the program of their
+/// command, following the rules of `hook_ids`.
+pub fn generate_hook_ids(hooks: &[(Option<&str>, Option<&str>)]) -> Vec<String> {
+    let mut taken: Vec<String> =
+        hooks.iter().filter_map(|(id, _)| id.map(|id| id.to_owned())).collect();
+    hooks
+        .iter()
+        .map(|(id, program)| {
+            if let Some(id) = id {
+                return (*id).to_owned();
+            }
+            let base = program
+                .and_then(|program| Path::new(program).file_stem())
+                .map_or_else(|| "hook".to_owned(), |stem| stem.to_string_lossy().into_owned());
+            let id = (1..)
+                .map(|n| if n == 1 { base.clone() } else { format!("{}-{}", base, n) })
+                .find(|id| !taken.contains(id))
+                .unwrap();
+            taken.push(id.clone());
+            id
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 pub struct HookCondition {
     /// Matches successful tasks.
-    #[serde(default = "Vec::new")]
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     pub successful: Vec<String>,
     /// Matches failed tasks.
-    #[serde(default = "Vec::new")]
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     pub failed: Vec<String>,
     /// Matches skipped tasks.
-    #[serde(default = "Vec::new")]
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     pub skipped: Vec<String>,
     /// Matches files.
-    #[serde(default = "Vec::new")]
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     pub exists: Vec<String>,
     /// Matches the current branch.
-    #[serde(default = "Vec::new")]
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     pub branch: Vec<String>,
 }
 
-/// Attempt to clone the repository at the given URL into the hook directory.
-pub fn fetch_package<S: AsRef<str>>(package: S) -> Result<Package, TackleError> {
-    let repo_url = package_into_git_url(package)?;
-    debug!("Repository URL: {}", repo_url);
-    let path = get_project_root()?.join(".tackle/hooks").join(&repo_url);
-    // if the work directory exists, PANIC!!
-    debug!("Checking if the package directory exists...");
-    if path.exists() {
-        panic!("attempting to clone into existing directory");
+/// Get the directory the tree of a repository at a commit is linked to in the
+/// project at `workdir`.
+fn checkout_path(workdir: &Path, source: &GitSource, commit: &str) -> PathBuf {
+    workdir.join(".tackle/hooks").join(source.key()).join(commit)
+}
+
+/// Resolve the directory of a package installed into the project at `workdir`
+/// at the given commit, as recorded by the manifest or the lockfile.
+pub fn resolve_package_directory<P: AsRef<Path>, S: AsRef<str>>(
+    workdir: P,
+    package: S,
+    commit: &str,
+) -> Result<PathBuf, TackleError> {
+    // packages inside the project are used in place
+    let path = match PackageSource::parse(package.as_ref())? {
+        PackageSource::Local(path) => workdir.as_ref().join(path),
+        PackageSource::Git(source) => {
+            checkout_path(workdir.as_ref(), &source, commit).join(source.path)
+        }
+    };
+    if !path.is_dir() {
+        return Err(TackleError::PackageNotInstalled(package.as_ref().to_owned()));
+    }
+    Ok(path)
+}
+
+/// Delete the checkout of a package at a commit from the project at `workdir`,
+/// unless a package in the lockfile uses the same repository at that commit.
+pub fn delete_package<P: AsRef<Path>, S: AsRef<str>>(
+    workdir: P,
+    package: S,
+    commit: &str,
+    lockfile: &TackleLockfile,
+) -> Result<(), TackleError> {
+    let source = match PackageSource::parse(package.as_ref())? {
+        PackageSource::Local(_) => {
+            debug!("Keeping '{}' inside the project", package.as_ref());
+            return Ok(());
+        }
+        PackageSource::Git(source) => source,
+    };
+    let key = source.key();
+    let shared =
+        lockfile.packages.iter().any(|locked| locked.repository == key && locked.commit == commit);
+    if shared {
+        debug!("Keeping repository '{}' at {:.7} used by other packages", key, commit);
+        return Ok(());
+    }
+    let hooks_dir = workdir.as_ref().join(".tackle/hooks");
+    let path = checkout_path(workdir.as_ref(), &source, commit);
+    debug!("Deleting '{}'...", path.display());
+    unlink_package_directory(&path)?;
+    // clean up the now empty repository, host and owner directories
+    for parent in path.ancestors().skip(1).take_while(|parent| *parent != hooks_dir) {
+        if fs::remove_dir(parent).is_err() {
+            break;
+        }
     }
+    Ok(())
+}
+
+/// Open the cached mirror of the repository containing a package, cloning it
+/// into the cache if it does not exist.
+pub fn open_package_repository<S: AsRef<str>>(package: S) -> Result<Repository, TackleError> {
+    open_source_repository(&GitSource::parse(package.as_ref())?)
+}
+
+/// Open the cached mirror of a repository, cloning it into the cache if it
+/// does not exist.
+fn open_source_repository(source: &GitSource) -> Result<Repository, TackleError> {
+    let clone_url = source.clone_url();
+    debug!("Repository URL: {}", clone_url);
+    open_or_clone(&clone_url, repository_path(source)?)
+}
+
+/// Find every package in the tree of a commit under the directory `path`,
+/// returning the path of each package from the repository root together with
+/// its manifest. Packages with an invalid manifest are skipped.
+pub fn find_packages(
+    repository: &Repository,
+    commit: &str,
+    path: &str,
+) -> Result<Vec<(String, Package)>, TackleError> {
+    let tree = commit_tree(repository, commit)?;
+    let mut packages = Vec::new();
+    let walked = tree.walk(TreeWalkMode::PreOrder, |directory, entry| {
+        if entry.name() != Some("package.toml") {
+            return TreeWalkResult::Ok;
+        }
+        let directory = match directory.trim_end_matches('/') {
+            "" => ".",
+            directory => directory,
+        };
+        let inside =
+            path == "." || directory == path || directory.starts_with(&format!("{}/", path));
+        let blob = entry.to_object(repository).and_then(|object| object.peel_to_blob());
+        if let (true, Ok(blob)) = (inside, blob) {
+            let contents = String::from_utf8_lossy(blob.content());
+            match toml::from_str::<Package>(&contents) {
+                Ok(package) => packages.push((directory.to_owned(), package)),
+                Err(err) => warn!("Skipping '{}/package.toml': {}", directory, err),
+            }
+        }
+        TreeWalkResult::Ok
+    });
+    walked.map_err(|_| TackleError::CommitNotFound(commit.to_owned()))?;
+    packages.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(packages)
+}
 
-    // clone the repository
-    debug!("Cloning repository...");
-    let repository = Repository::clone(&format!("https://{}.git", repo_url), path)
-        .map_err(|_| TackleError::RepositoryCloneFailed)?;
-    // get the manifest file
+/// Get the tree of a commit of a repository.
+fn commit_tree<'r>(repository: &'r Repository, commit: &str) -> Result<Tree<'r>, TackleError> {
+    Oid::from_str(commit)
+        .and_then(|oid| repository.find_commit(oid))
+        .and_then(|commit| commit.tree())
+        .map_err(|_| TackleError::CommitNotFound(commit.to_owned()))
+}
+
+/// Read the manifest of the package in the directory `path` of the tree of a
+/// commit, without exporting the tree.
+fn read_tree_package(
+    package: &str,
+    repository: &Repository,
+    commit: &str,
+    path: &str,
+) -> Result<Package, TackleError> {
+    let tree = commit_tree(repository, commit)?;
+    let file = match path {
+        "." => PathBuf::from("package.toml"),
+        path => Path::new(path).join("package.toml"),
+    };
+    let blob = tree
+        .get_path(&file)
+        .and_then(|entry| entry.to_object(repository))
+        .and_then(|object| object.peel_to_blob())
+        .map_err(|_| TackleError::PackageManifestNotFound(package.to_owned()))?;
+    Package::parse(file, &String::from_utf8_lossy(blob.content()))
+}
+
+/// A revision of a package picked while resolving dependencies.
+pub struct CandidatePackage {
+    /// The package manifest.
+    pub package: Package,
+    /// The SHA of the commit the revision resolved to.
+    pub commit: String,
+    /// The name of the tag or branch the revision resolved to.
+    pub version: Option<String>,
+}
+
+/// Resolve the requested revision of a package and read its manifest from the
+/// repository, without linking the package into the project at `workdir`. The
+/// head of the default branch is used if no revision is given.
+///
+/// When `offline` is set, the package is resolved only from the cache and
+/// vendored copies, and no repository is ever cloned or fetched. Packages
+/// inside the project are read in place, and have no commit.
+pub fn read_package_revision<S: AsRef<str>>(
+    workdir: &Path,
+    package: S,
+    revision: Option<&PackageRevision>,
+    offline: bool,
+) -> Result<CandidatePackage, TackleError> {
+    let source = match PackageSource::parse(package.as_ref())? {
+        PackageSource::Local(path) => {
+            return Ok(CandidatePackage {
+                package: read_package(package.as_ref(), &workdir.join(path))?,
+                commit: String::new(),
+                version: None,
+            });
+        }
+        PackageSource::Git(source) => source,
+    };
+    let (repository, commit, version) = if offline {
+        resolve_cached_revision(package.as_ref(), &source, revision)?
+    } else {
+        let repository = open_source_repository(&source)?;
+        let (commit, version) = resolve_revision(&repository, revision, false)?;
+        (Some(repository), commit, version)
+    };
+    let read = repository
+        .as_ref()
+        .map(|repository| read_tree_package(package.as_ref(), repository, &commit, &source.path));
+    let package = match read {
+        Some(Err(TackleError::CommitNotFound(_))) | None => {
+            read_cached_package(package.as_ref(), &source, &commit, workdir)?
+        }
+        Some(read) => read?,
+    };
+    Ok(CandidatePackage { package, commit, version })
+}
+
+/// A package fetched into the project.
+pub struct FetchedPackage {
+    /// The package manifest.
+    pub package: Package,
+    /// The SHA of the commit the package was fetched at.
+    pub commit: String,
+    /// The content hash of the package directory.
+    pub integrity: String,
+    /// The name of the tag or branch the package was fetched at.
+    pub version: Option<String>,
+}
+
+impl FetchedPackage {
+    /// Describe the fetched revision, preferring the tag or branch name.
+    pub fn label(&self) -> String {
+        match (&self.version, self.commit.is_empty()) {
+            (Some(version), _) => version.clone(),
+            (None, true) => "local".to_owned(),
+            (None, false) => self.commit[..7].to_owned(),
+        }
+    }
+}
+
+/// Fetch the requested revision of a package into the cache, and link the
+/// exported tree into the hook directory of the project at `workdir`. The head
+/// of the default branch is used if no revision is given.
+///
+/// When `offline` is set, the package is resolved only from the cache and
+/// vendored copies, and no repository is ever cloned or fetched. Packages
+/// inside the project are read in place, and have no commit or integrity hash.
+pub fn fetch_package<S: AsRef<str>>(
+    workdir: &Path,
+    package: S,
+    revision: Option<&PackageRevision>,
+    offline: bool,
+) -> Result<FetchedPackage, TackleError> {
+    let source = match PackageSource::parse(package.as_ref())? {
+        PackageSource::Local(path) => {
+            return Ok(FetchedPackage {
+                package: read_package(package.as_ref(), &workdir.join(path))?,
+                commit: String::new(),
+                integrity: String::new(),
+                version: None,
+            });
+        }
+        PackageSource::Git(source) => source,
+    };
+    let (commit, version) = if offline {
+        link_cached_package(package.as_ref(), &source, revision, workdir)?
+    } else {
+        let repository = open_source_repository(&source)?;
+        let (commit, version) = resolve_revision(&repository, revision, false)?;
+        let checkout = checkout_path(workdir, &source, &commit);
+        link_package_directory(&materialize_commit(&repository, &commit)?, &checkout)?;
+        (commit, version)
+    };
+
+    // read the package manifest
     debug!("Reading manifest file...");
-    let workdir = match repository.workdir() {
-        Some(workdir) => workdir,
-        None => panic!("workdir not found"),
+    let directory = checkout_path(workdir, &source, &commit).join(&source.path);
+    let package = read_package(package.as_ref(), &directory)?;
+    let integrity = compute_integrity(&directory)?;
+    Ok(FetchedPackage { package, commit, integrity, version })
+}
+
+/// Read the manifest of a package from its directory.
+fn read_package(package: &str, directory: &Path) -> Result<Package, TackleError> {
+    if !directory.join("package.toml").is_file() {
+        return Err(TackleError::PackageManifestNotFound(package.to_owned()));
+    }
+    Package::from_path(directory)
+}
+
+/// Read the manifest of a package at a commit missing from the cached
+/// repository, from the store or a vendored copy in the project at `workdir`.
+fn read_cached_package(
+    package: &str,
+    source: &GitSource,
+    commit: &str,
+    workdir: &Path,
+) -> Result<Package, TackleError> {
+    if let Some(cached) = lookup_package(source, commit)? {
+        return Ok(cached);
+    }
+    let checkout = checkout_path(workdir, source, commit);
+    if !is_vendored(&checkout, source) {
+        return Err(TackleError::PackageNotCached {
+            package: package.to_owned(),
+            commit: commit.to_owned(),
+        });
+    }
+    read_package(package, &checkout.join(&source.path))
+}
+
+/// Resolve a revision of a package from the cache, returning the cached
+/// repository if there is one, the SHA of the commit and the name of the
+/// chosen tag or branch. Commits missing from the cache are accepted as-is
+/// since vendored copies may be pinned to them.
+fn resolve_cached_revision(
+    package: &str,
+    source: &GitSource,
+    revision: Option<&PackageRevision>,
+) -> Result<(Option<Repository>, String, Option<String>), TackleError> {
+    let repository = lookup_repository(source)?.and_then(|path| Repository::open_bare(path).ok());
+    let label = || revision.map_or_else(|| "HEAD".to_owned(), |revision| revision.to_string());
+    let resolved =
+        repository.as_ref().map(|repository| resolve_revision(repository, revision, true));
+    let (commit, version) = match (resolved, revision) {
+        (Some(Ok(resolved)), _) => resolved,
+        // vendored copies may be pinned to commits missing from the cache
+        (
+            None | Some(Err(TackleError::CommitNotFound(_))),
+            Some(PackageRevision::Commit(commit)),
+        ) => (commit.clone(), None),
+        (Some(Err(err)), _) => {
+            return Err(TackleError::CachedRevisionFailed {
+                package: package.to_owned(),
+                commit: label(),
+                source: Box::new(err),
+            })
+        }
+        (None, _) => {
+            return Err(TackleError::PackageNotCached {
+                package: package.to_owned(),
+                commit: label(),
+            })
+        }
     };
-    // lookup the manifest path
-    let manifest_path = workdir.join("package.toml");
-    let manifest_path = match manifest_path.exists() && manifest_path.is_file() {
-        true => manifest_path,
-        false => panic!("manifest not found"),
+    Ok((repository, commit, version))
+}
+
+/// Resolve a revision of a package from the cache, and link it into the hook
+/// directory of the project at `workdir`. A vendored copy already in the hook
+/// directory is used as-is when the commit is not cached.
+fn link_cached_package(
+    package: &str,
+    source: &GitSource,
+    revision: Option<&PackageRevision>,
+    workdir: &Path,
+) -> Result<(String, Option<String>), TackleError> {
+    let (repository, commit, version) = resolve_cached_revision(package, source, revision)?;
+    let checkout = checkout_path(workdir, source, &commit);
+    let tree = match lookup_package(source, &commit)? {
+        Some(_) => Some(store_path(&commit)?),
+        None => repository.and_then(|repository| materialize_commit(&repository, &commit).ok()),
     };
+    match tree {
+        Some(tree) => link_package_directory(&tree, &checkout)?,
+        None if is_vendored(&checkout, source) => {
+            debug!("Using vendored copy of '{}' at '{}'", package, checkout.display());
+        }
+        None => {
+            return Err(TackleError::PackageNotCached { package: package.to_owned(), commit });
+        }
+    }
+    Ok((commit, version))
+}
+
+/// Test if the hook directory at `checkout` is a vendored copy of a package,
+/// i.e. a real directory rather than a link into the cache.
+fn is_vendored(checkout: &Path, source: &GitSource) -> bool {
+    let is_directory = fs::symlink_metadata(checkout).is_ok_and(|metadata| metadata.is_dir());
+    is_directory && checkout.join(&source.path).join("package.toml").is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use git2::{Repository, Signature};
+
+    use super::{
+        cache::lookup_repository, fetch_package, find_packages, read_tree_package,
+        source::GitSource,
+    };
+    use crate::{
+        errors::TackleError,
+        testing::{commit_files, init_origin, init_project, use_test_cache},
+        util::PackageRevision,
+    };
+
+    #[test]
+    fn test_find_packages() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let repository = Repository::init(root).unwrap();
+        fs::create_dir_all(root.join("hooks/lint")).unwrap();
+        fs::write(root.join("package.toml"), "name = \"root\"\n").unwrap();
+        fs::write(root.join("hooks/lint/package.toml"), "name = \"lint\"\n").unwrap();
+        fs::write(root.join("hooks/package.toml"), "name = [").unwrap();
+
+        let mut index = repository.index().unwrap();
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("tackle", "tackle@example.com").unwrap();
+        let commit = repository
+            .commit(None, &signature, &signature, "init", &tree, &[])
+            .unwrap()
+            .to_string();
+
+        let found = |path| -> Vec<_> {
+            let packages = find_packages(&repository, &commit, path).unwrap();
+            packages.into_iter().map(|(path, package)| (path, package.name.unwrap())).collect()
+        };
+        assert_eq!(
+            found("."),
+            vec![(".".to_owned(), "root".to_owned()), ("hooks/lint".to_owned(), "lint".to_owned())]
+        );
+        assert_eq!(found("hooks"), vec![("hooks/lint".to_owned(), "lint".to_owned())]);
+        assert!(found("hooks/fmt").is_empty());
+
+        let read = |path| read_tree_package("acme/hooks", &repository, &commit, path);
+        assert_eq!(read(".").unwrap().name.unwrap(), "root");
+        assert_eq!(read("hooks/lint").unwrap().name.unwrap(), "lint");
+        assert!(matches!(read("hooks"), Err(TackleError::PackageParseFailed { line: 1, .. })));
+        assert!(matches!(read("hooks/fmt"), Err(TackleError::PackageManifestNotFound(_))));
+    }
+
+    #[test]
+    fn test_fetch_offline() {
+        use_test_cache();
+        let project = init_project();
+        let workdir = project.path();
+        let (_origin, repository, url) = init_origin(".");
+        let first = commit_files(&repository, &[("package.toml", "name = \"lint\"\n")]);
+
+        // offline mode never clones a repository missing from the cache
+        let err = fetch_package(workdir, &url, None, true).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Cannot install '{}' offline, HEAD is not in the cache and has no vendored copy",
+                url
+            )
+        );
+        assert!(lookup_repository(&GitSource::parse(&url).unwrap()).unwrap().is_none());
 
-    // read and parse the manifest file
-    let manifest =
-        fs::read_to_string(&manifest_path).map_err(|_| TackleError::ManifestReadFailed)?;
+        // commits fetched once resolve from the cache
+        assert_eq!(fetch_package(workdir, &url, None, false).unwrap().commit, first);
+        let second = commit_files(&repository, &[("package.toml", "name = \"fmt\"\n")]);
+        let fetched = fetch_package(workdir, &url, None, true).unwrap();
+        assert_eq!((fetched.commit, fetched.package.name.unwrap()), (first, "lint".to_owned()));
 
-    match toml::from_str::<Package>(&manifest) {
-        Ok(manifest) => Ok(manifest),
-        Err(err) => panic!("manifest parse error: {}", err),
+        // newer commits are never fetched
+        let revision = PackageRevision::Commit(second.clone());
+        let err = fetch_package(workdir, &url, Some(&revision), true).err().unwrap();
+        assert!(matches!(
+            err,
+            TackleError::PackageNotCached { package, commit } if package == url && commit == second
+        ));
     }
 }
@@ -0,0 +1,249 @@
+//! Typed options declared by packages and set by the project manifest.
+//!
+//! A package declares its options in `package.toml`:
+//!
+//! ```toml
+//! [options.max_line]
+//! type = "int"
+//! default = 100
+//! description = "The maximum length of a line"
+//! ```
+//!
+//! and a project sets them on the manifest entry of the package, for each hook
+//! the package is installed for:
+//!
+//! ```toml
+//! [[hooks.pre-commit]]
+//! url = "acme/lint"
+//!
+//! [hooks.pre-commit.options]
+//! max_line = 120
+//! ```
+//!
+//! Option values are checked against their declared types whenever the
+//! manifest is loaded together with its packages. They replace `{{name}}`
+//! placeholders in hook commands, and are exported to hooks as
+//! `TACKLE_OPTION_<NAME>` environment variables.
+use std::{collections::BTreeMap, fmt};
+
+use serde::Deserialize;
+
+use crate::errors::TackleError;
+
+/// The prefix of the environment variables exposing option values to hooks.
+pub static OPTION_ENV_PREFIX: &str = "TACKLE_OPTION_";
+
+/// The type of a package option.
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum OptionType {
+    String,
+    Int,
+    Bool,
+    /// A list of strings.
+    List,
+}
+
+impl OptionType {
+    /// Every option type, as written in `package.toml`.
+    pub const NAMES: [&'static str; 4] = ["string", "int", "bool", "list"];
+
+    /// Check that a value has this type, converting it.
+    fn check(&self, value: &toml::Value) -> Option<OptionValue> {
+        match (self, value) {
+            (OptionType::String, toml::Value::String(value)) => {
+                Some(OptionValue::String(value.clone()))
+            }
+            (OptionType::Int, toml::Value::Integer(value)) => Some(OptionValue::Int(*value)),
+            (OptionType::Bool, toml::Value::Boolean(value)) => Some(OptionValue::Bool(*value)),
+            (OptionType::List, toml::Value::Array(items)) => items
+                .iter()
+                .map(|item| item.as_str().map(|item| item.to_owned()))
+                .collect::<Option<_>>()
+                .map(OptionValue::List),
+            _ => None,
+        }
+    }
+
+    /// Describe the type for an error message.
+    fn describe(&self) -> &'static str {
+        match self {
+            OptionType::String => "a string",
+            OptionType::Int => "an integer",
+            OptionType::Bool => "a boolean",
+            OptionType::List => "a list of strings",
+        }
+    }
+}
+
+impl fmt::Display for OptionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OptionType::String => "string",
+            OptionType::Int => "int",
+            OptionType::Bool => "bool",
+            OptionType::List => "list",
+        })
+    }
+}
+
+/// An option declared by a package.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PackageOption {
+    /// The type of the option.
+    #[serde(rename = "type")]
+    pub kind: OptionType,
+    /// The value used when the project does not set one. Options without a
+    /// default must be set by the project.
+    pub default: Option<toml::Value>,
+    /// A description of the option.
+    pub description: Option<String>,
+}
+
+/// The value of an option, checked against its declared type.
+#[derive(Debug, PartialEq, Clone)]
+pub enum OptionValue {
+    String(String),
+    Int(i64),
+    Bool(bool),
+    List(Vec<String>),
+}
+
+impl fmt::Display for OptionValue {
+    /// Format the value as it is exported to hooks. Lists are joined by newlines.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionValue::String(value) => f.write_str(value),
+            OptionValue::Int(value) => write!(f, "{}", value),
+            OptionValue::Bool(value) => write!(f, "{}", value),
+            OptionValue::List(items) => f.write_str(&items.join("\n")),
+        }
+    }
+}
+
+/// The values of the options of a package, keyed by name.
+pub type OptionValues = BTreeMap<String, OptionValue>;
+
+/// Check the option values set for a package against the options it
+/// declares, filling in the defaults of options which are not set.
+pub fn resolve_options(
+    package: &str,
+    declared: &BTreeMap<String, PackageOption>,
+    values: &BTreeMap<String, toml::Value>,
+) -> Result<OptionValues, TackleError> {
+    let invalid = |option: &str, reason: String| TackleError::InvalidOption {
+        package: package.to_owned(),
+        option: option.to_owned(),
+        reason,
+    };
+    if let Some(name) = values.keys().find(|name| !declared.contains_key(*name)) {
+        return Err(invalid(name, "the package does not declare it".to_owned()));
+    }
+    let mut resolved = OptionValues::new();
+    for (name, option) in declared {
+        let (value, origin) = match (values.get(name), &option.default) {
+            (Some(value), _) => (value, "value"),
+            (None, Some(default)) => (default, "default"),
+            (None, None) => return Err(invalid(name, "a value is required".to_owned())),
+        };
+        let value = option.kind.check(value).ok_or_else(|| {
+            let reason = format!(
+                "expected the {} to be {}, found {}",
+                origin,
+                option.kind.describe(),
+                value.type_str()
+            );
+            invalid(name, reason)
+        })?;
+        resolved.insert(name.clone(), value);
+    }
+    Ok(resolved)
+}
+
+/// Replace the `{{name}}` placeholders in a command with option values. An
+/// argument consisting of only a list placeholder is replaced by every item
+/// of the list; elsewhere, lists are joined by spaces. Placeholders of unknown
+/// options are left as-is.
+pub fn substitute_command(command: &[String], options: &OptionValues) -> Vec<String> {
+    let mut substituted = Vec::with_capacity(command.len());
+    for argument in command {
+        let whole = argument.strip_prefix("{{").and_then(|rest| rest.strip_suffix("}}"));
+        if let Some(OptionValue::List(items)) = whole.and_then(|name| options.get(name)) {
+            substituted.extend(items.iter().cloned());
+            continue;
+        }
+        let mut argument = argument.clone();
+        for (name, value) in options {
+            let value = match value {
+                OptionValue::List(items) => items.join(" "),
+                value => value.to_string(),
+            };
+            argument = argument.replace(&format!("{{{{{}}}}}", name), &value);
+        }
+        substituted.push(argument);
+    }
+    substituted
+}
+
+/// Get the environment variables exposing option values to hooks.
+pub fn option_env(options: &OptionValues) -> Vec<(String, String)> {
+    options
+        .iter()
+        .map(|(name, value)| {
+            let name: String = name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+                .collect();
+            (format!("{}{}", OPTION_ENV_PREFIX, name), value.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{
+        option_env, resolve_options, substitute_command, OptionType, OptionValue, PackageOption,
+    };
+
+    #[test]
+    fn test_resolve_and_substitute_options() {
+        let declared: BTreeMap<String, PackageOption> = toml::from_str(
+            r#"
+            max-line = { type = "int", default = 100 }
+            strict = { type = "bool", default = false }
+            paths = { type = "list" }
+            "#,
+        )
+        .unwrap();
+        let values = |toml: &str| toml::from_str::<BTreeMap<String, toml::Value>>(toml).unwrap();
+
+        let options =
+            resolve_options("acme/lint", &declared, &values("paths = [\"src\", \"lib\"]")).unwrap();
+        assert_eq!(options["max-line"], OptionValue::Int(100));
+        assert!(resolve_options("acme/lint", &declared, &values("")).is_err());
+        assert!(resolve_options("acme/lint", &declared, &values("paths = [1]")).is_err());
+        assert!(resolve_options("acme/lint", &declared, &values("paths = []\nmax = 1")).is_err());
+
+        let command: Vec<String> = ["lint", "--max-line={{max-line}}", "{{paths}}", "{{other}}"]
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect();
+        assert_eq!(
+            substitute_command(&command, &options),
+            vec!["lint", "--max-line=100", "src", "lib", "{{other}}"]
+        );
+        assert_eq!(
+            option_env(&options)[0],
+            ("TACKLE_OPTION_MAX_LINE".to_owned(), "100".to_owned())
+        );
+
+        // every type is written as it is named in package.toml
+        for name in OptionType::NAMES {
+            let kind: OptionType = toml::Value::from(name).try_into().unwrap();
+            assert_eq!(kind.to_string(), name);
+        }
+    }
+}
@@ -1 +1,528 @@
 //! This module contains the code for resolving and fetching packages.
+//!
+//! Packages may depend on other packages through the `[dependencies]` table of
+//! their `package.toml`. The resolver walks the dependency graph from the
+//! packages in the manifest, and picks one revision of every package that
+//! satisfies the requirements of all of its dependents.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use git2::Repository;
+use semver::{Version, VersionReq};
+
+use super::{
+    cache::lookup_repository,
+    download::{fetch_tags, local_tags},
+    fetch_package, open_package_repository, read_package_revision,
+    source::GitSource,
+    CandidatePackage, FetchedPackage,
+};
+use crate::{
+    errors::TackleError,
+    lockfile::LockedPackage,
+    util::{is_local_package, PackageRevision},
+};
+
+/// The maximum number of passes over the graph before giving up on finding a
+/// stable set of revisions.
+static MAX_RESOLVE_PASSES: usize = 64;
+
+/// Parse a tag name such as `v1.2.3` as a semver version.
+fn parse_tag_version(tag: &str) -> Option<Version> {
+    Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
+
+/// Select the tag matching a version specifier. A tag with exactly the same
+/// name is preferred, otherwise the specifier is parsed as a semver range and
+/// the highest matching tag is chosen.
+pub fn select_tag<S: AsRef<str>>(tags: &[S], specifier: &str) -> Result<String, TackleError> {
+    select_tag_matching_all(tags, &[specifier], None)?
+        .ok_or_else(|| TackleError::NoMatchingVersion(specifier.to_owned()))
+}
+
+/// Select the highest tag matching every version specifier, or `preferred` if
+/// it matches. A specifier naming an existing tag only matches that tag, any
+/// other specifier is parsed as a semver range.
+fn select_tag_matching_all<S: AsRef<str>>(
+    tags: &[S],
+    specifiers: &[&str],
+    preferred: Option<&str>,
+) -> Result<Option<String>, TackleError> {
+    let mut ranges = Vec::new();
+    let mut exact = Vec::new();
+    for specifier in specifiers {
+        if tags.iter().any(|tag| tag.as_ref() == *specifier) {
+            exact.push(*specifier);
+            continue;
+        }
+        let range = VersionReq::parse(specifier.strip_prefix('v').unwrap_or(specifier))
+            .map_err(|_| TackleError::InvalidVersion((*specifier).to_owned()))?;
+        ranges.push(range);
+    }
+    let candidates: Vec<&str> = tags
+        .iter()
+        .map(|tag| tag.as_ref())
+        .filter(|tag| exact.iter().all(|exact| tag == exact))
+        .filter(|tag| {
+            let version = parse_tag_version(tag);
+            ranges
+                .iter()
+                .all(|range| version.as_ref().is_some_and(|version| range.matches(version)))
+        })
+        .collect();
+    if let Some(preferred) = preferred.filter(|preferred| candidates.contains(preferred)) {
+        return Ok(Some(preferred.to_owned()));
+    }
+    Ok(candidates.into_iter().max_by_key(|tag| parse_tag_version(tag)).map(|tag| tag.to_owned()))
+}
+
+/// Select the tag with the highest semver version.
+pub fn latest_tag<S: AsRef<str>>(tags: &[S]) -> Option<String> {
+    tags.iter()
+        .filter_map(|tag| parse_tag_version(tag.as_ref()).map(|version| (version, tag.as_ref())))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, tag)| tag.to_owned())
+}
+
+/// A requirement on the revision of a package.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Requirement {
+    /// The package declaring the requirement, or `None` for the project manifest.
+    pub required_by: Option<String>,
+    /// The requested revision, or `None` for any revision.
+    pub revision: Option<PackageRevision>,
+}
+
+/// A package picked by the resolver.
+#[derive(Debug, PartialEq)]
+pub struct ResolvedPackage {
+    /// The revision satisfying every requirement on the package. Versions are
+    /// resolved to an exact tag name.
+    pub revision: Option<PackageRevision>,
+    /// The URLs of the packages this package depends on.
+    pub dependencies: Vec<String>,
+}
+
+/// Provides the resolver with the tags and dependencies of packages.
+pub trait PackageRegistry {
+    /// Get the tags of the repository containing a package.
+    fn tags(&mut self, url: &str) -> Result<Vec<String>, TackleError>;
+
+    /// Get the tag a package is locked to, which is preferred over newer tags.
+    fn locked_tag(&self, url: &str) -> Option<String>;
+
+    /// Get the dependencies of a package at a revision, mapped to the
+    /// requested revision of each dependency.
+    fn dependencies(
+        &mut self,
+        url: &str,
+        revision: Option<&PackageRevision>,
+    ) -> Result<BTreeMap<String, String>, TackleError>;
+}
+
+/// Resolve the dependency graph of the packages required by the manifest,
+/// picking one revision of every package which satisfies all of its
+/// requirements.
+pub fn resolve_dependencies<R: PackageRegistry>(
+    roots: &BTreeMap<String, Option<PackageRevision>>,
+    registry: &mut R,
+) -> Result<BTreeMap<String, ResolvedPackage>, TackleError> {
+    let mut requirements: BTreeMap<String, Vec<Requirement>> = roots
+        .iter()
+        .map(|(url, revision)| {
+            (url.clone(), vec![Requirement { required_by: None, revision: revision.clone() }])
+        })
+        .collect();
+    let mut resolved: BTreeMap<String, ResolvedPackage> = BTreeMap::new();
+    // the number of times the picked revision of each package changed
+    let mut changes: BTreeMap<String, usize> = BTreeMap::new();
+
+    // pick revisions until the requirements stop changing
+    for _ in 0..MAX_RESOLVE_PASSES {
+        let mut changed = false;
+        let urls: Vec<String> = requirements.keys().cloned().collect();
+        for url in urls {
+            let revision = choose_revision(&url, &requirements[&url], registry)?;
+            if resolved.get(&url).is_some_and(|package| package.revision == revision) {
+                continue;
+            }
+            // replace the requirements of the previously picked revision
+            let dependencies = registry.dependencies(&url, revision.as_ref())?;
+            for requirements in requirements.values_mut() {
+                requirements.retain(|requirement| requirement.required_by.as_ref() != Some(&url));
+            }
+            for (dependency, revision) in &dependencies {
+                requirements.entry(dependency.clone()).or_default().push(Requirement {
+                    required_by: Some(url.clone()),
+                    revision: PackageRevision::parse(revision),
+                });
+            }
+            let dependencies = dependencies.into_keys().collect();
+            *changes.entry(url.clone()).or_default() += 1;
+            resolved.insert(url, ResolvedPackage { revision, dependencies });
+            changed = true;
+        }
+
+        // forget packages which are no longer reachable from the manifest
+        let reachable = reachable_packages(roots.keys(), &resolved);
+        requirements.retain(|url, _| reachable.contains(url));
+        resolved.retain(|url, _| reachable.contains(url));
+        for requirements in requirements.values_mut() {
+            requirements.retain(|requirement| {
+                requirement.required_by.as_ref().is_none_or(|url| reachable.contains(url))
+            });
+        }
+
+        if !changed {
+            check_cycles(&resolved)?;
+            return Ok(resolved);
+        }
+    }
+    // the picked revisions keep changing each other's requirements, report the
+    // package whose revision changed the most
+    let (url, requirements) = requirements.iter().max_by_key(|(url, _)| changes.get(*url)).unwrap();
+    Err(TackleError::DependencyConflict {
+        package: url.clone(),
+        requirements: format_requirements(requirements),
+    })
+}
+
+/// Pick the revision of a package satisfying all of its requirements.
+fn choose_revision<R: PackageRegistry>(
+    url: &str,
+    requirements: &[Requirement],
+    registry: &mut R,
+) -> Result<Option<PackageRevision>, TackleError> {
+    let conflict = || TackleError::DependencyConflict {
+        package: url.to_owned(),
+        requirements: format_requirements(requirements),
+    };
+    let requested: Vec<_> =
+        requirements.iter().filter_map(|requirement| requirement.revision.as_ref()).collect();
+    // branches and commits cannot be compared with versions, so every
+    // requirement must name the same one
+    let pinned = requested.iter().find(|revision| !matches!(revision, PackageRevision::Version(_)));
+    if let Some(pinned) = pinned {
+        return match requested.iter().all(|revision| is_same_revision(revision, pinned)) {
+            true => Ok(Some((*pinned).clone())),
+            false => Err(conflict()),
+        };
+    }
+    let specifiers: Vec<&str> = requested
+        .iter()
+        .filter_map(|revision| match revision {
+            PackageRevision::Version(version) => Some(version.as_str()),
+            _ => None,
+        })
+        .collect();
+    if specifiers.is_empty() {
+        return Ok(None);
+    }
+    let tags = registry.tags(url)?;
+    let locked = registry.locked_tag(url);
+    match select_tag_matching_all(&tags, &specifiers, locked.as_deref())? {
+        Some(tag) => Ok(Some(PackageRevision::Version(tag))),
+        None if specifiers.len() == 1 => {
+            Err(TackleError::NoMatchingVersion(specifiers[0].to_owned()))
+        }
+        None => Err(conflict()),
+    }
+}
+
+/// Test if two requested revisions refer to the same revision, allowing
+/// abbreviated commits.
+fn is_same_revision(a: &PackageRevision, b: &PackageRevision) -> bool {
+    match (a, b) {
+        (PackageRevision::Commit(a), PackageRevision::Commit(b)) => {
+            a.starts_with(b.as_str()) || b.starts_with(a.as_str())
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// Format requirements for an error message.
+fn format_requirements(requirements: &[Requirement]) -> String {
+    requirements
+        .iter()
+        .map(|requirement| {
+            let revision = requirement
+                .revision
+                .as_ref()
+                .map_or_else(|| "*".to_owned(), |revision| revision.to_string());
+            match &requirement.required_by {
+                Some(url) => format!("{} (required by '{}')", revision, url),
+                None => format!("{} (required by the manifest)", revision),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Find the packages reachable from the given roots through their dependencies.
+fn reachable_packages<'a>(
+    roots: impl Iterator<Item = &'a String>,
+    resolved: &BTreeMap<String, ResolvedPackage>,
+) -> BTreeSet<String> {
+    let mut reachable = BTreeSet::new();
+    let mut pending: Vec<String> = roots.cloned().collect();
+    while let Some(url) = pending.pop() {
+        if let Some(package) = resolved.get(&url) {
+            pending.extend(
+                package.dependencies.iter().filter(|url| !reachable.contains(*url)).cloned(),
+            );
+        }
+        reachable.insert(url);
+    }
+    reachable
+}
+
+/// Fail if the dependency graph contains a cycle, reporting the packages on it.
+fn check_cycles(resolved: &BTreeMap<String, ResolvedPackage>) -> Result<(), TackleError> {
+    fn visit<'a>(
+        url: &'a str,
+        resolved: &'a BTreeMap<String, ResolvedPackage>,
+        path: &mut Vec<&'a str>,
+        done: &mut BTreeSet<&'a str>,
+    ) -> Result<(), TackleError> {
+        if let Some(start) = path.iter().position(|visiting| *visiting == url) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(url);
+            return Err(TackleError::DependencyCycle(cycle.join(" -> ")));
+        }
+        if done.contains(url) {
+            return Ok(());
+        }
+        path.push(url);
+        for dependency in resolved.get(url).map_or(&[][..], |package| &package.dependencies) {
+            visit(dependency, resolved, path, done)?;
+        }
+        path.pop();
+        done.insert(url);
+        Ok(())
+    }
+
+    let mut done = BTreeSet::new();
+    for url in resolved.keys() {
+        visit(url, resolved, &mut Vec::new(), &mut done)?;
+    }
+    Ok(())
+}
+
+/// A registry which fetches the repositories of packages into the cache, and
+/// reads the dependencies of each revision from its tree. Packages in the
+/// lockfile are read at their locked commit while the picked revision still
+/// matches it. Nothing is linked into the project until the picked revisions
+/// are fetched.
+pub struct FetchingRegistry {
+    workdir: PathBuf,
+    offline: bool,
+    locked: BTreeMap<String, LockedPackage>,
+    tags: BTreeMap<String, Vec<String>>,
+    /// The last picked revision of every package.
+    candidates: BTreeMap<String, CandidatePackage>,
+}
+
+impl FetchingRegistry {
+    /// Create a registry for the project at `workdir`, which prefers the given
+    /// locked packages.
+    pub fn new(
+        workdir: &Path,
+        offline: bool,
+        locked: impl IntoIterator<Item = LockedPackage>,
+    ) -> FetchingRegistry {
+        FetchingRegistry {
+            workdir: workdir.to_owned(),
+            offline,
+            locked: locked.into_iter().map(|locked| (locked.url.clone(), locked)).collect(),
+            tags: BTreeMap::new(),
+            candidates: BTreeMap::new(),
+        }
+    }
+
+    /// Link the revisions picked for the resolved packages into the project.
+    pub fn fetch(
+        mut self,
+        resolved: &BTreeMap<String, ResolvedPackage>,
+    ) -> Result<BTreeMap<String, FetchedPackage>, TackleError> {
+        let mut packages = BTreeMap::new();
+        for url in resolved.keys() {
+            let candidate = self.candidates.remove(url).unwrap();
+            let commit = PackageRevision::Commit(candidate.commit);
+            let mut fetched = fetch_package(&self.workdir, url, Some(&commit), self.offline)?;
+            fetched.version = candidate.version;
+            // the lockfile guarantees the exact contents of the package
+            let locked = self.locked.get(url).filter(|locked| locked.commit == fetched.commit);
+            if let Some(locked) = locked.filter(|locked| locked.integrity != fetched.integrity) {
+                return Err(TackleError::IntegrityMismatch {
+                    package: url.to_owned(),
+                    expected: locked.integrity.clone(),
+                    actual: fetched.integrity,
+                });
+            }
+            packages.insert(url.clone(), fetched);
+        }
+        Ok(packages)
+    }
+}
+
+impl PackageRegistry for FetchingRegistry {
+    fn tags(&mut self, url: &str) -> Result<Vec<String>, TackleError> {
+        if let Some(tags) = self.tags.get(url) {
+            return Ok(tags.clone());
+        }
+        let mut tags = match (is_local_package(url), self.offline) {
+            (true, _) => Vec::new(),
+            (false, true) => match lookup_repository(&GitSource::parse(url)?)? {
+                Some(path) => Repository::open_bare(path)
+                    .map_err(|_| TackleError::RepositoryFetchFailed)
+                    .and_then(|repository| local_tags(&repository))?,
+                None => Vec::new(),
+            },
+            (false, false) => fetch_tags(&open_package_repository(url)?)?,
+        };
+        // the locked tag is known to exist even if the repository is not cached
+        if let Some(tag) = self.locked_tag(url).filter(|tag| !tags.contains(tag)) {
+            tags.push(tag);
+        }
+        self.tags.insert(url.to_owned(), tags.clone());
+        Ok(tags)
+    }
+
+    fn locked_tag(&self, url: &str) -> Option<String> {
+        self.locked.get(url).and_then(|locked| locked.version.clone())
+    }
+
+    fn dependencies(
+        &mut self,
+        url: &str,
+        revision: Option<&PackageRevision>,
+    ) -> Result<BTreeMap<String, String>, TackleError> {
+        let locked = self.locked.get(url).filter(|locked| match revision {
+            None => locked.version.is_none(),
+            Some(PackageRevision::Version(name) | PackageRevision::Branch(name)) => {
+                locked.version.as_ref() == Some(name)
+            }
+            Some(PackageRevision::Commit(commit)) => locked.commit.starts_with(commit.as_str()),
+        });
+        let candidate = match locked {
+            Some(locked) => {
+                let commit = PackageRevision::Commit(locked.commit.clone());
+                let mut candidate =
+                    read_package_revision(&self.workdir, url, Some(&commit), self.offline)?;
+                candidate.version = locked.version.clone();
+                candidate
+            }
+            None => read_package_revision(&self.workdir, url, revision, self.offline)?,
+        };
+        let dependencies = candidate.package.dependencies.clone();
+        self.candidates.insert(url.to_owned(), candidate);
+        Ok(dependencies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{latest_tag, resolve_dependencies, select_tag, PackageRegistry};
+    use crate::{errors::TackleError, util::PackageRevision};
+
+    /// The tags of a package, each with its dependencies.
+    type MockTags = Vec<(&'static str, Vec<(&'static str, &'static str)>)>;
+
+    /// A registry of packages whose dependencies are keyed by tag.
+    struct MockRegistry(BTreeMap<&'static str, MockTags>);
+
+    impl PackageRegistry for MockRegistry {
+        fn tags(&mut self, url: &str) -> Result<Vec<String>, TackleError> {
+            Ok(self.0[url].iter().map(|(tag, _)| tag.to_string()).collect())
+        }
+
+        fn locked_tag(&self, _: &str) -> Option<String> {
+            None
+        }
+
+        fn dependencies(
+            &mut self,
+            url: &str,
+            revision: Option<&PackageRevision>,
+        ) -> Result<BTreeMap<String, String>, TackleError> {
+            let tag = revision.map(|revision| revision.to_string());
+            let (_, dependencies) = self.0[url]
+                .iter()
+                .find(|(name, _)| tag.as_deref().is_none_or(|tag| tag == *name))
+                .unwrap();
+            Ok(dependencies
+                .iter()
+                .map(|(url, version)| (url.to_string(), version.to_string()))
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_select_tag() {
+        let tags = ["v1.0.0", "v1.2.0", "v1.4.1", "v2.0.0", "latest", "1.3.0"];
+        assert_eq!(select_tag(&tags, "v1.2.0").unwrap(), "v1.2.0");
+        assert_eq!(select_tag(&tags, "latest").unwrap(), "latest");
+        assert_eq!(select_tag(&tags, "^1.2").unwrap(), "v1.4.1");
+        assert_eq!(select_tag(&tags, "~1.2").unwrap(), "v1.2.0");
+        assert_eq!(select_tag(&tags, ">=2").unwrap(), "v2.0.0");
+        assert!(select_tag(&tags, "^3").is_err());
+        assert!(select_tag(&tags, "not a version").is_err());
+        assert_eq!(latest_tag(&tags).unwrap(), "v2.0.0");
+        assert_eq!(latest_tag(&["main"]), None);
+    }
+
+    #[test]
+    fn test_resolve_dependencies() {
+        let mut registry = MockRegistry(BTreeMap::from([
+            ("acme/lint", vec![("v1.0.0", vec![("acme/node", "^1")])]),
+            ("acme/fmt", vec![("v1.0.0", vec![("acme/node", "~1.1")])]),
+            ("acme/node", vec![("v1.1.0", vec![]), ("v1.2.0", vec![]), ("v2.0.0", vec![])]),
+            ("acme/loop", vec![("v1.0.0", vec![("acme/back", "*")])]),
+            ("acme/back", vec![("v1.0.0", vec![("acme/loop", "*")])]),
+            (
+                "acme/ping",
+                vec![("v1.0.0", vec![("acme/pong", "^2")]), ("v2.0.0", vec![("acme/pong", "^1")])],
+            ),
+            ("acme/pong", vec![("v1.0.0", vec![("acme/ping", "^1")]), ("v2.0.0", vec![])]),
+            ("acme/zlib", vec![("v1.0.0", vec![])]),
+        ]));
+        let version = |version: &str| Some(PackageRevision::Version(version.to_owned()));
+        let roots = BTreeMap::from([
+            ("acme/lint".to_owned(), version("^1")),
+            ("acme/fmt".to_owned(), version("^1")),
+        ]);
+        // shared dependencies pick the highest tag satisfying every requirement
+        let resolved = resolve_dependencies(&roots, &mut registry).unwrap();
+        assert_eq!(resolved["acme/node"].revision, version("v1.1.0"));
+        assert_eq!(resolved["acme/lint"].dependencies, vec!["acme/node"]);
+
+        let roots = BTreeMap::from([
+            ("acme/lint".to_owned(), version("^1")),
+            ("acme/node".to_owned(), version("^2")),
+        ]);
+        let err = resolve_dependencies(&roots, &mut registry).unwrap_err();
+        assert!(
+            matches!(err, TackleError::DependencyConflict { package, .. } if package == "acme/node")
+        );
+
+        let roots = BTreeMap::from([("acme/loop".to_owned(), None)]);
+        let err = resolve_dependencies(&roots, &mut registry).unwrap_err();
+        assert!(
+            matches!(err, TackleError::DependencyCycle(cycle) if cycle == "acme/back -> acme/loop -> acme/back")
+        );
+
+        // the revisions of ping and pong keep changing each other's requirements
+        let roots = BTreeMap::from([
+            ("acme/ping".to_owned(), version("*")),
+            ("acme/zlib".to_owned(), version("*")),
+        ]);
+        let err = resolve_dependencies(&roots, &mut registry).unwrap_err();
+        assert!(
+            matches!(err, TackleError::DependencyConflict { package, .. } if package == "acme/pong")
+        );
+    }
+}
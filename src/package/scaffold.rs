@@ -0,0 +1,205 @@
+//! Generates the files of new hook packages.
+use std::{fmt::Write, fs, path::Path, str::FromStr};
+
+use log::{debug, info};
+
+use crate::{errors::TackleError, hooks::Hook};
+
+/// The annotated package manifest, without the hook definitions.
+static MANIFEST_TEMPLATE: &str = include_str!("../assets/package/package.toml");
+/// The description of the `tests/` directory.
+static TESTS_TEMPLATE: &str = include_str!("../assets/package/tests.md");
+
+/// The language of the scripts generated for a new package.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Template {
+    Shell,
+    Python,
+    Node,
+}
+
+impl Template {
+    /// Every template supported by Tackle.
+    pub const ALL: [Template; 3] = [Template::Shell, Template::Python, Template::Node];
+
+    /// The name of the template on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Template::Shell => "shell",
+            Template::Python => "python",
+            Template::Node => "node",
+        }
+    }
+
+    /// The file extension of the generated scripts.
+    fn extension(&self) -> &'static str {
+        match self {
+            Template::Shell => "sh",
+            Template::Python => "py",
+            Template::Node => "js",
+        }
+    }
+
+    /// The interpreter the generated scripts need.
+    fn interpreter(&self) -> &'static str {
+        match self {
+            Template::Shell => "sh",
+            Template::Python => "python3",
+            Template::Node => "node",
+        }
+    }
+
+    /// The contents of a generated script.
+    fn script(&self) -> &'static str {
+        match self {
+            Template::Shell => include_str!("../assets/package/hook.sh"),
+            Template::Python => include_str!("../assets/package/hook.py"),
+            Template::Node => include_str!("../assets/package/hook.js"),
+        }
+    }
+}
+
+impl FromStr for Template {
+    type Err = TackleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Template::ALL
+            .into_iter()
+            .find(|template| template.name() == s)
+            .ok_or_else(|| TackleError::UnknownTemplate(s.to_owned()))
+    }
+}
+
+/// Test if a package name can be written into the generated files as-is. The
+/// name ends up in TOML, Python and JavaScript strings and shell scripts, so it
+/// is limited to characters none of them treat specially.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Fill the placeholders of a template.
+fn render(template: &str, name: &str, hook: &str) -> String {
+    template.replace("{{name}}", name).replace("{{hook}}", hook)
+}
+
+/// Generate the manifest of a new package with one example hook per git hook.
+fn generate_manifest(name: &str, hooks: &[Hook], template: Template) -> String {
+    let mut manifest = render(MANIFEST_TEMPLATE, name, "");
+    for (i, hook) in hooks.iter().enumerate() {
+        let hook = hook.git_name();
+        manifest.push('\n');
+        writeln!(manifest, "[[hooks.{}]]", hook).unwrap();
+        writeln!(manifest, "id = \"{}\"", hook).unwrap();
+        writeln!(manifest, "command = [\"./hooks/{}.{}\"]", hook, template.extension()).unwrap();
+        writeln!(manifest, "dependencies = [\"{}\"]", template.interpreter()).unwrap();
+        // show how hooks refer to each other once
+        if i == 0 {
+            manifest.push_str(&format!(
+                "\n# A hook which only runs after the hook above succeeded.\n\
+                 # [[hooks.{0}]]\n\
+                 # id = \"report\"\n\
+                 # command = [\"echo\", \"{0} passed\"]\n\
+                 # blocking = false\n\
+                 # conditions = [{{ successful = [\"{0}\"] }}]\n",
+                hook
+            ));
+        }
+    }
+    manifest
+}
+
+/// Write a file of a new package, creating its parent directories.
+fn write_file(path: &Path, contents: &str) -> Result<(), TackleError> {
+    debug!("Writing '{}'...", path.display());
+    let write_failed = |source| TackleError::PackageWriteFailed { file: path.to_owned(), source };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(write_failed)?;
+    }
+    fs::write(path, contents).map_err(write_failed)
+}
+
+/// Generate a new package in the directory at `path`, with an example hook
+/// and script for every git hook in `hooks`, and a `tests/` fixture layout.
+/// The directory must be empty or missing. When `git` is set, the directory is
+/// also made into a git repository.
+pub fn scaffold_package<P: AsRef<Path>>(
+    path: P,
+    name: &str,
+    hooks: &[Hook],
+    template: Template,
+    git: bool,
+) -> Result<(), TackleError> {
+    let path = path.as_ref();
+    if !is_valid_name(name) {
+        return Err(TackleError::InvalidPackageName(name.to_owned()));
+    }
+    let is_empty = fs::read_dir(path).map_or(true, |mut entries| entries.next().is_none());
+    if !is_empty {
+        return Err(TackleError::DirectoryNotEmpty(path.to_owned()));
+    }
+    info!("Creating package '{}' in '{}'...", name, path.display());
+    write_file(&path.join("package.toml"), &generate_manifest(name, hooks, template))?;
+    for hook in hooks {
+        let hook = hook.git_name();
+        let script = path.join("hooks").join(format!("{}.{}", hook, template.extension()));
+        write_file(&script, &render(template.script(), name, hook))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o755))
+                .map_err(|source| TackleError::PackageWriteFailed { file: script, source })?;
+        }
+        for outcome in ["pass", "fail"] {
+            write_file(&path.join("tests").join(hook).join(outcome).join(".gitkeep"), "")?;
+        }
+    }
+    write_file(&path.join("tests/README.md"), &render(TESTS_TEMPLATE, name, ""))?;
+    if git {
+        debug!("Initializing a git repository...");
+        git2::Repository::init(path).map_err(|_| TackleError::RepositoryInitFailed)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scaffold_package, Template};
+    use crate::{
+        errors::TackleError,
+        hooks::Hook,
+        package::{
+            lint::{lint_package, Severity},
+            Package,
+        },
+    };
+
+    #[test]
+    fn test_scaffold_package() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join("lint");
+        let hooks = [Hook::PreCommit, Hook::CommitMsg];
+        scaffold_package(&root, "lint", &hooks, Template::Python, true).unwrap();
+
+        let package = Package::from_path(&root).unwrap();
+        assert_eq!(package.name.as_deref(), Some("lint"));
+        let command = &package.hooks.0[&Hook::CommitMsg][0].command;
+        assert_eq!(command, &vec!["./hooks/commit-msg.py".to_owned()]);
+        let ids: Vec<_> = package.hooks.0.values().map(|hooks| hooks[0].id.clone()).collect();
+        assert_eq!(ids, vec![Some("pre-commit".to_owned()), Some("commit-msg".to_owned())]);
+        assert!(root.join("hooks/pre-commit.py").is_file());
+        assert!(root.join("tests/commit-msg/fail/.gitkeep").is_file());
+        assert!(root.join(".git").is_dir());
+        // the generated manifest passes the linter
+        let diagnostics = lint_package(&root).unwrap();
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.severity != Severity::Error));
+
+        assert!(scaffold_package(&root, "lint", &hooks, Template::Shell, false).is_err());
+        let root = temp.path().join("quoted");
+        let err = scaffold_package(&root, "say \"hi\"", &hooks, Template::Node, false);
+        assert!(matches!(err, Err(TackleError::InvalidPackageName(_))));
+        assert!(!root.exists());
+        assert_eq!("node".parse::<Template>().unwrap(), Template::Node);
+        assert!("ruby".parse::<Template>().is_err());
+    }
+}
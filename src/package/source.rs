@@ -0,0 +1,309 @@
+//! Parses package URLs into structured package sources.
+use std::path::{Path, PathBuf};
+
+use git2::Repository;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{errors::TackleError, util::is_local_package};
+
+lazy_static! {
+    static ref URL_REGEX: Regex =
+        Regex::new(r"^[a-zA-Z0-9][a-zA-Z0-9-]{1,61}[a-zA-Z0-9]\.[a-zA-Z]{2,}").unwrap();
+}
+
+/// The protocol used to clone a repository.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Protocol {
+    /// `https://host/owner/repo`, also used for shorthand package names.
+    Https,
+    /// `http://host/owner/repo`.
+    Http,
+    /// `ssh://user@host/owner/repo`.
+    Ssh,
+    /// SSH, written scp-style as `user@host:owner/repo`.
+    Scp,
+    /// `git://host/owner/repo`.
+    Git,
+    /// `file:///path/to/repo`.
+    File,
+}
+
+impl Protocol {
+    /// The port used when a URL does not give one.
+    fn default_port(self) -> Option<u16> {
+        match self {
+            Protocol::Https => Some(443),
+            Protocol::Http => Some(80),
+            Protocol::Ssh | Protocol::Scp => Some(22),
+            Protocol::Git => Some(9418),
+            Protocol::File => None,
+        }
+    }
+}
+
+/// A package inside a git repository.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GitSource {
+    /// The protocol used to clone the repository.
+    pub protocol: Protocol,
+    /// The user to authenticate as, e.g. `git`.
+    pub user: Option<String>,
+    /// The git server. Empty for `file://` repositories.
+    pub host: String,
+    /// The port of the git server, if not the default.
+    pub port: Option<u16>,
+    /// The path of the repository on the server, as written in the URL.
+    pub repository: String,
+    /// The path from the repository root to the package directory.
+    pub path: String,
+}
+
+impl GitSource {
+    /// Parse a package URL. Accepts `owner/repo` shorthands for GitHub,
+    /// `host.tld/owner/repo` shorthands, `https://`, `http://`, `ssh://`,
+    /// `git://` and `file://` URLs, and scp-style `user@host:owner/repo`.
+    pub fn parse(url: &str) -> Result<GitSource, TackleError> {
+        let invalid = || TackleError::InvalidPackageUrl(url.to_owned());
+        if is_local_package(url) {
+            return Err(invalid());
+        }
+        if let Some(path) = url.strip_prefix("file://") {
+            if path.split('/').any(is_relative_segment) {
+                return Err(invalid());
+            }
+            return Ok(GitSource::parse_file(Path::new(path)));
+        }
+        if let Some((scheme, rest)) = url.split_once("://") {
+            let protocol = match scheme {
+                "https" => Protocol::Https,
+                "http" => Protocol::Http,
+                "ssh" | "git+ssh" => Protocol::Ssh,
+                "git" => Protocol::Git,
+                _ => return Err(invalid()),
+            };
+            let (authority, path) = rest.split_once('/').ok_or_else(invalid)?;
+            let (user, host) = split_user(authority);
+            let (host, port) = match host.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port.parse().map_err(|_| invalid())?)),
+                None => (host, None),
+            };
+            return GitSource::from_parts(protocol, user, host, port, path).ok_or_else(invalid);
+        }
+        // scp-style URLs have a colon before any slash
+        if let Some((authority, path)) = url.split_once(':') {
+            if !authority.contains('/') {
+                let (user, host) = split_user(authority);
+                return GitSource::from_parts(Protocol::Scp, user, host, None, path)
+                    .ok_or_else(invalid);
+            }
+        }
+        // if the name does not start with a domain, then assume it is a github repo
+        let (host, path) = match url.split_once('/') {
+            Some((host, path)) if URL_REGEX.is_match(host) => (host, path),
+            _ => ("github.com", url),
+        };
+        let mut source =
+            GitSource::from_parts(Protocol::Https, None, host, None, path).ok_or_else(invalid)?;
+        if !source.repository.ends_with(".git") {
+            source.repository.push_str(".git");
+        }
+        Ok(source)
+    }
+
+    /// Build a source from the parts of a URL, splitting the path into the
+    /// repository and the package directory inside it. The repository ends at
+    /// the first segment with a `.git` suffix, or after `owner/repo`. Paths
+    /// with `.` or `..` segments are rejected, as they could point outside the
+    /// directories of the repository in the cache and the project.
+    fn from_parts(
+        protocol: Protocol,
+        user: Option<&str>,
+        host: &str,
+        port: Option<u16>,
+        path: &str,
+    ) -> Option<GitSource> {
+        let segments: Vec<_> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+        let end =
+            segments.iter().position(|segment| segment.ends_with(".git")).map_or(2, |i| i + 1);
+        if host.is_empty() || segments.len() < end {
+            return None;
+        }
+        if segments.iter().any(|segment| is_relative_segment(segment)) {
+            return None;
+        }
+        Some(GitSource {
+            protocol,
+            user: user.map(|user| user.to_owned()),
+            host: host.to_lowercase(),
+            port: port.filter(|port| Some(*port) != protocol.default_port()),
+            repository: segments[..end].join("/"),
+            path: package_path(&segments[end..].join("/")),
+        })
+    }
+
+    /// Build a source from the path of a `file://` URL, where the repository
+    /// is the closest ancestor directory that git can open.
+    fn parse_file(path: &Path) -> GitSource {
+        let root = path.ancestors().find(|dir| Repository::open(dir).is_ok()).unwrap_or(path);
+        GitSource {
+            protocol: Protocol::File,
+            user: None,
+            host: String::new(),
+            port: None,
+            repository: root.to_string_lossy().into_owned(),
+            path: package_path(&path.strip_prefix(root).unwrap().to_string_lossy()),
+        }
+    }
+
+    /// Get the URL to clone the repository from.
+    pub fn clone_url(&self) -> String {
+        let user = self.user.as_ref().map_or_else(String::new, |user| format!("{}@", user));
+        let port = self.port.map_or_else(String::new, |port| format!(":{}", port));
+        let scheme = match self.protocol {
+            Protocol::File => return format!("file://{}", self.repository),
+            Protocol::Scp => return format!("{}{}:{}", user, self.host, self.repository),
+            Protocol::Https => "https",
+            Protocol::Http => "http",
+            Protocol::Ssh => "ssh",
+            Protocol::Git => "git",
+        };
+        format!("{}://{}{}{}/{}", scheme, user, self.host, port, self.repository)
+    }
+
+    /// Get the canonical key of the repository, which is the same for every
+    /// URL pointing at it regardless of protocol. This names the directories
+    /// of the repository in the cache and the project.
+    pub fn key(&self) -> String {
+        let repository = self.repository.strip_suffix(".git").unwrap_or(&self.repository);
+        match (self.protocol, self.port) {
+            (Protocol::File, _) => format!("file{}", repository),
+            (_, Some(port)) => format!("{}_{}/{}", self.host, port, repository),
+            (_, None) => format!("{}/{}", self.host, repository),
+        }
+    }
+}
+
+/// Where a package is installed from.
+#[derive(Debug, PartialEq, Clone)]
+pub enum PackageSource {
+    /// A directory inside the project, relative to the project root.
+    Local(PathBuf),
+    /// A package inside a git repository.
+    Git(GitSource),
+}
+
+impl PackageSource {
+    /// Parse a package URL, or a `./` path to a directory inside the project.
+    pub fn parse(url: &str) -> Result<PackageSource, TackleError> {
+        match is_local_package(url) {
+            true => Ok(PackageSource::Local(PathBuf::from(url))),
+            false => Ok(PackageSource::Git(GitSource::parse(url)?)),
+        }
+    }
+}
+
+/// Split the user from the authority of a URL.
+fn split_user(authority: &str) -> (Option<&str>, &str) {
+    match authority.split_once('@') {
+        Some((user, host)) => (Some(user), host),
+        None => (None, authority),
+    }
+}
+
+/// Test if a segment of a path refers to the current or the parent directory.
+fn is_relative_segment(segment: &str) -> bool {
+    matches!(segment, "." | "..")
+}
+
+/// Normalise the path to a package inside a repository, using `.` for the root.
+fn package_path(path: &str) -> String {
+    match path {
+        "" => ".".to_owned(),
+        path => path.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GitSource, PackageSource, Protocol};
+    use crate::errors::TackleError;
+
+    #[test]
+    fn test_url_regex() {
+        let url = "github.com/rust-lang/rust";
+        assert!(super::URL_REGEX.is_match(url));
+        let url = "rust-lang/rust";
+        assert!(!super::URL_REGEX.is_match(url));
+    }
+
+    #[test]
+    fn test_parse_shorthand() {
+        let source = GitSource::parse("skyezerfox/hooks").unwrap();
+        assert_eq!(source.key(), "github.com/skyezerfox/hooks");
+        assert_eq!(source.clone_url(), "https://github.com/skyezerfox/hooks.git");
+        assert_eq!(source.path, ".");
+
+        let source = GitSource::parse("github.com/skyezerfox/hooks/some/sub/directories").unwrap();
+        assert_eq!(source.key(), "github.com/skyezerfox/hooks");
+        assert_eq!(source.path, "some/sub/directories");
+
+        let source = GitSource::parse("mygitserver.com/skyezerfox/hooks/deep/project").unwrap();
+        assert_eq!(source.key(), "mygitserver.com/skyezerfox/hooks");
+        assert_eq!(source.path, "deep/project");
+
+        assert!(GitSource::parse("hooks").is_err());
+        assert!(GitSource::parse("./tools/hooks").is_err());
+    }
+
+    #[test]
+    fn test_parse_urls() {
+        let source = GitSource::parse("git@git.acme.dev:team/hooks.git/lint").unwrap();
+        assert_eq!(source.protocol, Protocol::Scp);
+        assert_eq!(source.clone_url(), "git@git.acme.dev:team/hooks.git");
+        assert_eq!(source.key(), "git.acme.dev/team/hooks");
+        assert_eq!(source.path, "lint");
+
+        let source = GitSource::parse("ssh://git@git.acme.dev:2222/team/hooks").unwrap();
+        assert_eq!(source.clone_url(), "ssh://git@git.acme.dev:2222/team/hooks");
+        assert_eq!(source.key(), "git.acme.dev_2222/team/hooks");
+
+        let source = GitSource::parse("https://Git.Acme.dev:443/group/sub/hooks.git").unwrap();
+        assert_eq!(source.clone_url(), "https://git.acme.dev/group/sub/hooks.git");
+        assert_eq!(source.key(), "git.acme.dev/group/sub/hooks");
+        assert_eq!(source.path, ".");
+
+        let source = GitSource::parse("git://git.acme.dev/team/hooks/lint").unwrap();
+        assert_eq!(source.clone_url(), "git://git.acme.dev/team/hooks");
+        assert_eq!(source.path, "lint");
+
+        // every form of the same repository shares a key
+        for url in ["acme/lint", "git@github.com:acme/lint.git", "https://github.com/acme/lint"] {
+            assert_eq!(GitSource::parse(url).unwrap().key(), "github.com/acme/lint");
+        }
+
+        assert!(GitSource::parse("ftp://acme.dev/team/hooks").is_err());
+        assert!(GitSource::parse("https://acme.dev:port/team/hooks").is_err());
+        // paths cannot leave the repository
+        let urls = ["acme/..", "acme/lint/../../etc", "git@acme.dev:./hooks", "file:///tmp/../etc"];
+        for url in urls {
+            assert!(matches!(GitSource::parse(url), Err(TackleError::InvalidPackageUrl(_))));
+        }
+    }
+
+    #[test]
+    fn test_parse_file_url() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        git2::Repository::init(root).unwrap();
+        let source = GitSource::parse(&format!("file://{}/tools/lint", root.display())).unwrap();
+        assert_eq!(source.clone_url(), format!("file://{}", root.display()));
+        assert_eq!(source.key(), format!("file{}", root.display()));
+        assert_eq!(source.path, "tools/lint");
+
+        assert_eq!(
+            PackageSource::parse("./tools/lint").unwrap(),
+            PackageSource::Local("./tools/lint".into())
+        );
+    }
+}
@@ -1,5 +1,6 @@
 //! Contains types and methods for interacting with a project where Tackle is installed.
 use std::{
+    collections::{BTreeMap, BTreeSet},
     fs,
     path::{Path, PathBuf},
     sync::Mutex,
@@ -7,45 +8,441 @@ use std::{
 
 use lazy_static::lazy_static;
 use log::debug;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 
-use crate::errors::TackleError;
+use crate::{
+    errors::TackleError,
+    hooks::Hook,
+    package::{
+        hook_ids,
+        options::{resolve_options, OptionValues, PackageOption},
+        HookCondition, HookDefinition,
+    },
+    util::is_local_package,
+};
 
 /// The default manifest file.
-pub static DEFAULT_MANIFEST: &'static str = include_str!("assets/tackle.toml");
+pub static DEFAULT_MANIFEST: &str = include_str!("assets/tackle.toml");
 /// The default gitignore file.
-pub static DEFAULT_GITIGNORE: &'static str = include_str!("assets/.gitignore");
+pub static DEFAULT_GITIGNORE: &str = include_str!("assets/.gitignore");
 
 lazy_static! {
     /// The path to the project root. This is cached to avoid repeated calls to `get_project_root`.
     pub static ref TACKLE_DIR: Mutex<Option<String>> = Mutex::new(None);
 }
 
+/// A package installed into the project.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(try_from = "RawManifestHook", into = "RawManifestHook")]
 pub struct TackleManifestHook {
+    /// The package URL, or a `./` path for packages inside the project.
     pub url: String,
+    /// The requested revision of the package: a tag, a semver range, a
+    /// `#branch`, a commit SHA, or `*` for the head of the default branch.
     pub version: String,
+    /// The SHA of the commit the package was installed at.
+    pub commit: String,
+    /// The content hash of the package directory.
     pub integrity: String,
+    /// The IDs of the only hook definitions of the package to run.
+    pub only: Vec<String>,
+    /// The IDs of hook definitions of the package not to run.
+    pub skip: Vec<String>,
+    /// Changes to hook definitions of the package, keyed by hook ID.
+    pub overrides: BTreeMap<String, HookOverride>,
+    /// Values for the options declared by the package, keyed by option name.
+    pub options: BTreeMap<String, toml::Value>,
+}
+
+/// Changes made by the project to a hook definition of a package. Fields
+/// which are not set keep the value from the package.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct HookOverride {
+    /// Replaces the command of the hook.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<Vec<String>>,
+    /// Arguments appended to the command of the hook.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Replaces the OS-level dependencies of the hook.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dependencies: Option<Vec<String>>,
+    /// Replaces whether a failure of the hook aborts the git operation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocking: Option<bool>,
+    /// Replaces whether the hook must run on its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<bool>,
+    /// Replaces the conditions of the hook.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<Vec<HookCondition>>,
+    /// Disables the hook when set to `false`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+}
+
+impl HookOverride {
+    /// Merge the override into a hook definition, returning the names of the
+    /// fields it changed.
+    pub fn apply(&self, definition: &mut HookDefinition) -> Vec<&'static str> {
+        let mut overridden = Vec::new();
+        if let Some(command) = &self.command {
+            definition.command = command.clone();
+            overridden.push("command");
+        }
+        if !self.args.is_empty() {
+            definition.command.extend(self.args.iter().cloned());
+            overridden.push("args");
+        }
+        if let Some(dependencies) = &self.dependencies {
+            definition.dependencies = dependencies.clone();
+            overridden.push("dependencies");
+        }
+        if let Some(blocking) = self.blocking {
+            definition.blocking = blocking;
+            overridden.push("blocking");
+        }
+        if let Some(serial) = self.serial {
+            definition.serial = serial;
+            overridden.push("serial");
+        }
+        if let Some(conditions) = &self.conditions {
+            definition.conditions = conditions.clone();
+            overridden.push("conditions");
+        }
+        if self.enabled.is_some() {
+            overridden.push("enabled");
+        }
+        overridden
+    }
+}
+
+/// A hook definition of a package, as configured by the project.
+pub struct EffectiveHook {
+    /// The definition with the overrides of the project merged in.
+    pub definition: HookDefinition,
+    /// Whether the hook runs, i.e. it is selected and not disabled.
+    pub active: bool,
+    /// The names of the fields changed by the project.
+    pub overridden: Vec<&'static str>,
+}
+
+impl TackleManifestHook {
+    /// Test if the entry enables the hook definition of the package with the
+    /// given ID, which may be generated for hooks without one.
+    pub fn is_selected(&self, id: &str) -> bool {
+        let listed = |ids: &[String]| ids.iter().any(|listed| listed == id);
+        match self.only.is_empty() {
+            true => !listed(&self.skip),
+            false => listed(&self.only),
+        }
+    }
+
+    /// Check that every ID in `only`, `skip` and `overrides` refers to a
+    /// definition of the package for the given hook.
+    pub fn check_selection(
+        &self,
+        hook: Hook,
+        definitions: &[HookDefinition],
+    ) -> Result<(), TackleError> {
+        let defined = hook_ids(definitions);
+        let is_defined = |id: &&String| defined.contains(id);
+        let ids = self.only.iter().chain(&self.skip).chain(self.overrides.keys());
+        match ids.into_iter().find(|id| !is_defined(id)) {
+            Some(id) => {
+                Err(TackleError::UnknownHookId { package: self.url.clone(), hook, id: id.clone() })
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Check the option values set by the entry against the options the
+    /// package declares, filling in the defaults of options which are not set.
+    pub fn option_values(
+        &self,
+        declared: &BTreeMap<String, PackageOption>,
+    ) -> Result<OptionValues, TackleError> {
+        resolve_options(&self.url, declared, &self.options)
+    }
+
+    /// Check the selection of the package's definitions for a hook, and merge
+    /// the overrides of the project into them. Definitions without an ID are
+    /// given their generated ID, which overrides of the command do not change.
+    pub fn effective_hooks(
+        &self,
+        hook: Hook,
+        definitions: Vec<HookDefinition>,
+    ) -> Result<Vec<EffectiveHook>, TackleError> {
+        self.check_selection(hook, &definitions)?;
+        let ids = hook_ids(&definitions);
+        let effective = definitions.into_iter().zip(ids).map(|(mut definition, id)| {
+            let hook_override = self.overrides.get(&id);
+            let enabled = hook_override.and_then(|hook_override| hook_override.enabled);
+            let active = self.is_selected(&id) && enabled != Some(false);
+            definition.id = Some(id);
+            let overridden = hook_override
+                .map_or_else(Vec::new, |hook_override| hook_override.apply(&mut definition));
+            EffectiveHook { definition, active, overridden }
+        });
+        Ok(effective.collect())
+    }
+}
+
+/// A manifest entry as written in the manifest file, where packages inside
+/// the project are given by `path` rather than `url`.
+#[derive(Serialize, Deserialize)]
+struct RawManifestHook {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    commit: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    integrity: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    only: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skip: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    overrides: BTreeMap<String, HookOverride>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    options: BTreeMap<String, toml::Value>,
+}
+
+impl TryFrom<RawManifestHook> for TackleManifestHook {
+    type Error = String;
+
+    fn try_from(raw: RawManifestHook) -> Result<Self, Self::Error> {
+        let url = match (raw.url, raw.path) {
+            (Some(url), None) => url,
+            // paths are relative to the project root
+            (None, Some(path)) if is_local_package(&path) => path,
+            (None, Some(path)) => format!("./{}", path),
+            (Some(_), Some(_)) => return Err("expected only one of `url` or `path`".to_owned()),
+            (None, None) => return Err("missing field `url` or `path`".to_owned()),
+        };
+        if !raw.only.is_empty() && !raw.skip.is_empty() {
+            return Err("expected only one of `only` or `skip`".to_owned());
+        }
+        Ok(TackleManifestHook {
+            url,
+            version: raw.version.unwrap_or_else(|| "*".to_owned()),
+            commit: raw.commit,
+            integrity: raw.integrity,
+            only: raw.only,
+            skip: raw.skip,
+            overrides: raw.overrides,
+            options: raw.options,
+        })
+    }
+}
+
+impl From<TackleManifestHook> for RawManifestHook {
+    fn from(entry: TackleManifestHook) -> Self {
+        if is_local_package(&entry.url) {
+            let path = entry.url.strip_prefix("./").unwrap_or(&entry.url).to_owned();
+            return RawManifestHook {
+                url: None,
+                path: Some(path),
+                version: None,
+                commit: String::new(),
+                integrity: String::new(),
+                only: entry.only,
+                skip: entry.skip,
+                overrides: entry.overrides,
+                options: entry.options,
+            };
+        }
+        RawManifestHook {
+            url: Some(entry.url),
+            path: None,
+            version: Some(entry.version),
+            commit: entry.commit,
+            integrity: entry.integrity,
+            only: entry.only,
+            skip: entry.skip,
+            overrides: entry.overrides,
+            options: entry.options,
+        }
+    }
+}
+
+/// An entry of the manifest for a git hook.
+#[derive(Debug, Serialize, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum TackleManifestEntry {
+    /// A package installed into the project.
+    Package(TackleManifestHook),
+    /// A hook defined in the manifest itself, run from the project root.
+    Inline(HookDefinition),
+}
+
+impl TackleManifestEntry {
+    /// Get the package of the entry, if it is not an inline hook.
+    pub fn package(&self) -> Option<&TackleManifestHook> {
+        match self {
+            TackleManifestEntry::Package(package) => Some(package),
+            TackleManifestEntry::Inline(_) => None,
+        }
+    }
+
+    /// Get the package of the entry mutably, if it is not an inline hook.
+    fn package_mut(&mut self) -> Option<&mut TackleManifestHook> {
+        match self {
+            TackleManifestEntry::Package(package) => Some(package),
+            TackleManifestEntry::Inline(_) => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TackleManifestEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // entries without a package source are hook definitions
+        let value = toml::Value::deserialize(deserializer)?;
+        let entry = match value.get("url").is_some() || value.get("path").is_some() {
+            true => value.try_into().map(TackleManifestEntry::Package),
+            false => value.try_into().map(TackleManifestEntry::Inline),
+        };
+        entry.map_err(de::Error::custom)
+    }
+}
+
+/// The packages and inline hooks installed for each git hook.
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Clone)]
+#[serde(transparent)]
+pub struct TackleManifestHooks(pub BTreeMap<Hook, Vec<TackleManifestEntry>>);
+
+impl TackleManifestHooks {
+    /// Get the packages and inline hooks installed for a hook.
+    pub fn get(&self, hook: Hook) -> &[TackleManifestEntry] {
+        self.0.get(&hook).map_or(&[], |hooks| hooks.as_slice())
+    }
+
+    /// Iterate over the package entries of every hook. A package installed for
+    /// several hooks has one entry per hook.
+    pub fn packages(&self) -> impl Iterator<Item = &TackleManifestHook> {
+        self.0.values().flatten().filter_map(TackleManifestEntry::package)
+    }
+
+    /// Iterate mutably over the package entries of every hook.
+    pub fn packages_mut(&mut self) -> impl Iterator<Item = &mut TackleManifestHook> {
+        self.0.values_mut().flatten().filter_map(TackleManifestEntry::package_mut)
+    }
+
+    /// Register a package for the given hooks, updating any existing entries
+    /// in place and removing it from hooks it no longer provides. Existing
+    /// entries keep the hook selection, overrides and options set by the project.
+    pub fn insert_package(&mut self, hooks: &[Hook], entry: TackleManifestHook) {
+        let is_entry = |existing: &TackleManifestEntry| {
+            existing.package().is_some_and(|existing| existing.url == entry.url)
+        };
+        for (hook, entries) in self.0.iter_mut() {
+            if !hooks.contains(hook) {
+                entries.retain(|existing| !is_entry(existing));
+                continue;
+            }
+            match entries.iter_mut().find(|existing| is_entry(existing)) {
+                Some(TackleManifestEntry::Package(existing)) => {
+                    existing.version = entry.version.clone();
+                    existing.commit = entry.commit.clone();
+                    existing.integrity = entry.integrity.clone();
+                }
+                _ => entries.push(TackleManifestEntry::Package(entry.clone())),
+            }
+        }
+        for hook in hooks {
+            self.0
+                .entry(*hook)
+                .or_insert_with(|| vec![TackleManifestEntry::Package(entry.clone())]);
+        }
+        self.0.retain(|_, entries| !entries.is_empty());
+    }
+
+    /// Unregister a package from every hook, returning whether it was installed.
+    pub fn remove_package(&mut self, url: &str) -> bool {
+        let installed = self.contains_package(url);
+        for entries in self.0.values_mut() {
+            entries.retain(|entry| entry.package().is_none_or(|entry| entry.url != url));
+        }
+        self.0.retain(|_, entries| !entries.is_empty());
+        installed
+    }
+
+    /// Find the entry of an installed package.
+    pub fn find_package(&self, url: &str) -> Option<&TackleManifestHook> {
+        self.packages().find(|entry| entry.url == url)
+    }
+
+    /// Get the URLs of every installed package, without duplicates.
+    pub fn package_urls(&self) -> BTreeSet<String> {
+        self.packages().map(|entry| entry.url.clone()).collect()
+    }
+
+    /// Test if a package is installed for any hook.
+    pub fn contains_package(&self, url: &str) -> bool {
+        self.packages().any(|entry| entry.url == url)
+    }
+
+    /// Get the entries to run for a hook. Packages inside the project are read
+    /// afresh on every run, so those installed only for other hooks are added
+    /// as well, without a hook selection, in case they now define the hook.
+    pub fn run_entries(&self, hook: Hook) -> Vec<TackleManifestEntry> {
+        let mut entries = self.get(hook).to_vec();
+        for entry in self.packages().filter(|entry| is_local_package(&entry.url)) {
+            let added = entries
+                .iter()
+                .any(|added| added.package().is_some_and(|added| added.url == entry.url));
+            if !added {
+                entries.push(TackleManifestEntry::Package(TackleManifestHook {
+                    only: Vec::new(),
+                    skip: Vec::new(),
+                    overrides: BTreeMap::new(),
+                    ..entry.clone()
+                }));
+            }
+        }
+        entries
+    }
+
+    /// Get the hooks which have at least one package or inline hook installed.
+    pub fn used_hooks(&self) -> Vec<Hook> {
+        self.0.iter().filter(|(_, hooks)| !hooks.is_empty()).map(|(hook, _)| *hook).collect()
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TackleManifestHooks {
-    #[serde(default = "Vec::new")]
-    pub precommit: Vec<TackleManifestHook>,
-    #[serde(default = "Vec::new")]
-    pub postcommit: Vec<TackleManifestHook>,
-    #[serde(default = "Vec::new")]
-    pub prepush: Vec<TackleManifestHook>,
-    #[serde(default = "Vec::new")]
-    pub postpush: Vec<TackleManifestHook>,
+/// Project-wide settings.
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq)]
+pub struct TackleSettings {
+    /// URLs of the package indexes used to resolve bare package names, in
+    /// order of precedence.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indexes: Vec<String>,
+    /// The number of hooks run in parallel, as if `--jobs` was passed to
+    /// `tackle run`. Defaults to 1, running the hooks one at a time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+}
+
+impl TackleSettings {
+    fn is_default(&self) -> bool {
+        *self == TackleSettings::default()
+    }
 }
 
 /// The manifest file.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct TackleManifest {
     /// The manifest version.
     pub version: String,
+    /// Project-wide settings.
+    #[serde(default, skip_serializing_if = "TackleSettings::is_default")]
+    pub settings: TackleSettings,
     /// A list of installed hooks.
+    #[serde(default)]
     pub hooks: TackleManifestHooks,
 }
 
@@ -58,10 +455,11 @@ pub fn read_manifest<P: AsRef<Path>>(workdir: P) -> Result<TackleManifest, Tackl
     let path = workdir.as_ref().join(".tackle/tackle.toml");
     let contents = fs::read_to_string(&path).map_err(|_| TackleError::ManifestReadFailed)?;
     let manifest: TackleManifest =
-        toml::from_str(&contents).map_err(|err| TackleError::ManifestParseFailed(err))?;
+        toml::from_str(&contents).map_err(TackleError::ManifestParseFailed)?;
     Ok(manifest)
 }
 
+/// Write the manifest file.
 pub fn write_manifest<P: AsRef<Path>>(
     workdir: P,
     manifest: &TackleManifest,
@@ -76,13 +474,12 @@ pub fn write_manifest<P: AsRef<Path>>(
 pub fn create_tackle_directory<P: AsRef<Path>>(workdir: P) -> Result<(), TackleError> {
     let path = workdir.as_ref().join(".tackle");
     if !path.exists() {
-        fs::create_dir_all(&path).map_err(|err| TackleError::CreateTackleDirectoryFailed(err))?;
+        fs::create_dir_all(&path).map_err(TackleError::CreateTackleDirectoryFailed)?;
     }
     // create the empty hooks directory
     let hooks_dir = &path.join("hooks");
     if !hooks_dir.exists() {
-        fs::create_dir_all(&hooks_dir)
-            .map_err(|err| TackleError::CreateTackleDirectoryFailed(err))?;
+        fs::create_dir_all(hooks_dir).map_err(TackleError::CreateTackleDirectoryFailed)?;
     }
     // write the default manifest
     let manifest_path = &path.join("tackle.toml");
@@ -102,7 +499,7 @@ pub fn get_project_root() -> Result<PathBuf, TackleError> {
     let repo =
         git2::Repository::discover(&cwd).map_err(|_err| TackleError::RepositoryDiscoveryFailed)?;
     // check repoistory work directory exists
-    if let None = repo.workdir() {
+    if repo.workdir().is_none() {
         return Err(TackleError::RepositoryDiscoveryFailed);
     }
     let project_root = repo.workdir().unwrap().to_owned();
@@ -114,10 +511,10 @@ pub fn get_project_root() -> Result<PathBuf, TackleError> {
 pub fn is_initialized() -> bool {
     debug!("Checking initialization state of project...");
     let project_root = get_project_root();
-    if let Err(_) = project_root {
+    if project_root.is_err() {
         return false;
     }
-    return check_tackle_directory_exists(&project_root.unwrap());
+    check_tackle_directory_exists(project_root.unwrap())
 }
 
 /// Test if the tackle directory exists.
@@ -125,3 +522,159 @@ pub fn check_tackle_directory_exists<P: AsRef<Path>>(workdir: P) -> bool {
     let path = workdir.as_ref().join(".tackle");
     path.exists() && path.is_dir()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{TackleManifest, TackleManifestEntry, TackleManifestHook, TackleManifestHooks};
+    use crate::{hooks::Hook, package::Package};
+
+    fn entry(url: &str, commit: &str) -> TackleManifestHook {
+        TackleManifestHook {
+            url: url.to_owned(),
+            version: "*".to_owned(),
+            commit: commit.to_owned(),
+            integrity: "".to_owned(),
+            only: Vec::new(),
+            skip: Vec::new(),
+            overrides: Default::default(),
+            options: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_insert_and_remove_package() {
+        let mut hooks = TackleManifestHooks::default();
+        hooks.insert_package(&[Hook::PreCommit, Hook::CommitMsg], entry("acme/lint", "a"));
+        hooks.insert_package(&[Hook::PreCommit], entry("acme/fmt", "a"));
+        assert_eq!(hooks.used_hooks(), vec![Hook::PreCommit, Hook::CommitMsg]);
+
+        // re-adding updates the entry in place, keeping its hook selection
+        let lint = hooks.packages_mut().next().unwrap();
+        lint.skip.push("fmt".to_owned());
+        hooks.insert_package(&[Hook::PreCommit], entry("acme/lint", "b"));
+        let lint = hooks.get(Hook::PreCommit)[0].package().unwrap();
+        assert_eq!(lint.commit, "b");
+        assert_eq!(lint.skip, vec!["fmt"]);
+        assert!(hooks.get(Hook::CommitMsg).is_empty());
+
+        assert!(hooks.remove_package("acme/lint"));
+        assert!(!hooks.remove_package("acme/lint"));
+        let fmt = TackleManifestEntry::Package(entry("acme/fmt", "a"));
+        assert_eq!(hooks.get(Hook::PreCommit), std::slice::from_ref(&fmt));
+
+        // packages inside the project run for every hook they may define
+        let mut local = entry("./tools/hooks", "");
+        local.skip.push("msg".to_owned());
+        hooks.insert_package(&[Hook::CommitMsg], local);
+        let local = TackleManifestEntry::Package(entry("./tools/hooks", ""));
+        assert_eq!(hooks.run_entries(Hook::PreCommit), vec![fmt, local]);
+        assert_eq!(hooks.run_entries(Hook::CommitMsg), hooks.get(Hook::CommitMsg));
+    }
+
+    #[test]
+    fn test_inline_hooks() {
+        let manifest: TackleManifest = toml::from_str(
+            r#"
+            version = "1"
+
+            [[hooks.pre-commit]]
+            url = "acme/lint"
+
+            [hooks.pre-commit.options]
+            max = 1
+
+            [[hooks.pre-commit]]
+            id = "local"
+            command = ["cargo", "fmt", "--check"]
+            conditions = [{ successful = ["lint"] }]
+            "#,
+        )
+        .unwrap();
+        let entries = manifest.hooks.get(Hook::PreCommit);
+        assert_eq!(entries[0].package().unwrap().url, "acme/lint");
+        assert_eq!(entries[0].package().unwrap().options["max"], 1.into());
+        match &entries[1] {
+            TackleManifestEntry::Inline(definition) => {
+                assert_eq!(definition.id.as_deref(), Some("local"));
+                assert_eq!(definition.conditions[0].successful, vec!["lint"]);
+            }
+            entry => panic!("expected an inline hook, found {:?}", entry),
+        }
+        assert_eq!(manifest.hooks.packages().count(), 1);
+
+        // inline hooks survive rewriting the manifest
+        let mut hooks = manifest.hooks.clone();
+        assert!(hooks.remove_package("acme/lint"));
+        assert_eq!(hooks.get(Hook::PreCommit), &entries[1..]);
+        let written = toml::to_string(&manifest).unwrap();
+        assert_eq!(toml::from_str::<TackleManifest>(&written).unwrap(), manifest);
+
+        // entries which are neither packages nor hooks are rejected
+        assert!(toml::from_str::<TackleManifest>(
+            "version = \"1\"\n[[hooks.pre-commit]]\nid = \"x\""
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_select_hooks() {
+        let package: Package = toml::from_str(
+            r#"
+            [[hooks.pre-commit]]
+            id = "fmt"
+            command = ["fmt"]
+
+            [[hooks.pre-commit]]
+            id = "lint"
+            command = ["lint"]
+
+            [[hooks.pre-commit]]
+            command = ["anonymous"]
+            "#,
+        )
+        .unwrap();
+        let definitions = package.hooks.into_hook(Hook::PreCommit);
+        let selected = |entry: &TackleManifestHook| -> Vec<_> {
+            let effective = entry.effective_hooks(Hook::PreCommit, definitions.clone()).unwrap();
+            let active = effective.iter().filter(|hook| hook.active);
+            active.map(|hook| hook.definition.id.clone().unwrap()).collect()
+        };
+
+        let mut lint = entry("acme/lint", "a");
+        assert_eq!(selected(&lint), vec!["fmt", "lint", "anonymous"]);
+        lint.only = vec!["lint".to_owned()];
+        assert_eq!(selected(&lint), vec!["lint"]);
+        lint.only = Vec::new();
+        lint.skip = vec!["lint".to_owned()];
+        assert_eq!(selected(&lint), vec!["fmt", "anonymous"]);
+        lint.skip = vec!["missing".to_owned()];
+        assert!(lint.check_selection(Hook::PreCommit, &definitions).is_err());
+        // hooks without an ID are selected by their generated ID
+        lint.skip = Vec::new();
+        lint.only = vec!["anonymous".to_owned()];
+        assert_eq!(selected(&lint), vec!["anonymous"]);
+        lint.only = Vec::new();
+
+        lint.overrides = toml::from_str(
+            r#"
+            fmt = { command = ["fmt", "--check"], args = ["src"], blocking = false }
+            lint = { enabled = false }
+            anonymous = { command = ["renamed"] }
+            "#,
+        )
+        .unwrap();
+        let effective = lint.effective_hooks(Hook::PreCommit, definitions.clone()).unwrap();
+        assert_eq!(effective[0].definition.command, vec!["fmt", "--check", "src"]);
+        assert!(!effective[0].definition.blocking);
+        assert_eq!(effective[0].overridden, vec!["command", "args", "blocking"]);
+        assert!(!effective[1].active);
+        assert_eq!(effective[2].definition.id.as_deref(), Some("anonymous"));
+        assert_eq!(effective[2].definition.command, vec!["renamed"]);
+        assert_eq!(selected(&lint), vec!["fmt", "anonymous"]);
+        lint.overrides.insert("missing".to_owned(), Default::default());
+        assert!(lint.check_selection(Hook::PreCommit, &definitions).is_err());
+
+        let both = "url = \"acme/lint\"\nonly = [\"fmt\"]\nskip = [\"lint\"]";
+        assert!(toml::from_str::<TackleManifestHook>(both).is_err());
+    }
+}
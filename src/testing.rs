@@ -0,0 +1,71 @@
+//! Fixtures shared by the tests of several modules.
+use std::fs;
+
+use git2::{IndexAddOption, Repository, Signature};
+use lazy_static::lazy_static;
+use tempfile::TempDir;
+
+use crate::{package::cache::CACHE_DIR_ENV, project::create_tackle_directory};
+
+lazy_static! {
+    /// The cache used by the tests, in place of the cache of the user.
+    static ref TEST_CACHE_DIR: TempDir = {
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var(CACHE_DIR_ENV, dir.path());
+        dir
+    };
+}
+
+/// Point the package cache at a directory private to the tests. This must be
+/// called before anything resolves the cache directory.
+pub fn use_test_cache() {
+    lazy_static::initialize(&TEST_CACHE_DIR);
+}
+
+/// Create a git repository to install packages from, returning its directory
+/// and the `file://` URL of the package at `path` inside it.
+pub fn init_origin(path: &str) -> (TempDir, Repository, String) {
+    let temp = tempfile::tempdir().unwrap();
+    let repository = Repository::init(temp.path()).unwrap();
+    let url = match path {
+        "." => format!("file://{}", temp.path().display()),
+        path => format!("file://{}/{}", temp.path().display(), path),
+    };
+    (temp, repository, url)
+}
+
+/// Write files into the work tree of a repository and commit them on top of
+/// `HEAD`, returning the SHA of the new commit.
+pub fn commit_files(repository: &Repository, files: &[(&str, &str)]) -> String {
+    let root = repository.workdir().unwrap();
+    for (path, contents) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    let mut index = repository.index().unwrap();
+    index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+    index.write().unwrap();
+    let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("tackle", "tackle@example.com").unwrap();
+    let parent = repository.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<_> = parent.iter().collect();
+    repository
+        .commit(Some("HEAD"), &signature, &signature, "commit", &tree, &parents)
+        .unwrap()
+        .to_string()
+}
+
+/// Tag a commit of a repository.
+pub fn tag_commit(repository: &Repository, name: &str, commit: &str) {
+    let object = repository.revparse_single(commit).unwrap();
+    repository.tag_lightweight(name, &object, false).unwrap();
+}
+
+/// Create a project initialized with Tackle.
+pub fn init_project() -> TempDir {
+    let temp = tempfile::tempdir().unwrap();
+    Repository::init(temp.path()).unwrap();
+    create_tackle_directory(temp.path()).unwrap();
+    temp
+}
@@ -1,11 +1,9 @@
 //! Contains various utilites and useful methods.
-use std::{env, fs};
+use std::{env, fmt, fs};
 
 use lazy_static::lazy_static;
 use regex::Regex;
 
-use crate::errors::TackleError;
-
 /// Test if the target executable exists in path.
 pub fn is_program_in_path(program: &str) -> bool {
     let delimiter = if cfg!(windows) { ";" } else { ":" };
@@ -21,56 +19,72 @@ pub fn is_program_in_path(program: &str) -> bool {
 }
 
 lazy_static! {
-    static ref URL_REGEX: Regex =
-        Regex::new(r"^[a-zA-Z0-9][a-zA-Z0-9-]{1,61}[a-zA-Z0-9]\.[a-zA-Z]{2,}").unwrap();
+    static ref COMMIT_REGEX: Regex = Regex::new(r"^[0-9a-f]{7,40}$").unwrap();
 }
 
-/// Translate a package name into a valid Git repository URL.
-pub fn package_into_git_url<S: AsRef<str>>(url: S) -> Result<String, TackleError> {
-    let repo_url = resolve_package_url(url)?;
-    let repo_url = repo_url.split("/").take(3).collect::<Vec<_>>().join("/");
-    Ok(repo_url)
+/// A revision of a package repository requested by the user.
+#[derive(Debug, PartialEq, Clone)]
+pub enum PackageRevision {
+    /// A tag name or a semver range matched against the repository tags.
+    Version(String),
+    /// The head of a branch.
+    Branch(String),
+    /// An exact commit SHA.
+    Commit(String),
 }
 
-/// Resolve a package name into a valid path.
-pub fn resolve_package_url<S: AsRef<str>>(url: S) -> Result<String, TackleError> {
-    let url = url.as_ref();
-    // package must have at least one slash
-    if !url.contains('/') {
-        panic!("invalid url name");
+impl PackageRevision {
+    /// Parse a revision as written after a package name, or in the `version`
+    /// field of the manifest. Returns `None` for the default branch.
+    pub fn parse(revision: &str) -> Option<PackageRevision> {
+        match revision {
+            "" | "*" => None,
+            _ if revision.starts_with('#') => {
+                Some(PackageRevision::Branch(revision[1..].to_owned()))
+            }
+            _ if COMMIT_REGEX.is_match(revision) => {
+                Some(PackageRevision::Commit(revision.to_owned()))
+            }
+            _ => Some(PackageRevision::Version(revision.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for PackageRevision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackageRevision::Version(version) => f.write_str(version),
+            PackageRevision::Branch(branch) => write!(f, "#{}", branch),
+            PackageRevision::Commit(commit) => f.write_str(commit),
+        }
     }
-    // if url does not contain a tld, then assume it is a github repo
-    let tld = url.split('/').next().unwrap();
-    let repo_url = match URL_REGEX.is_match(tld) {
-        true => url.to_owned(),
-        false => format!("github.com/{}", url),
-    };
-    Ok(repo_url)
 }
 
-/// Extracts the relative path from the repository root to a package's directory from its repository URL.
-pub fn extract_package_path<S: AsRef<str>>(url: S) -> Result<String, TackleError> {
-    let url = resolve_package_url(url)?;
-    // skip the git server and repository names
-    let last = url.split('/').skip(3).collect::<Vec<_>>().join("/");
-    if last == "" {
-        Ok(".".to_owned())
-    } else {
-        Ok(last)
+/// Split a package specifier such as `owner/repo@^1.2` or `owner/repo#main`
+/// into the package name and the requested revision.
+pub fn split_package_specifier(specifier: &str) -> (String, Option<PackageRevision>) {
+    if let Some((package, branch)) = specifier.split_once('#') {
+        return (package.to_owned(), PackageRevision::parse(&format!("#{}", branch)));
+    }
+    match specifier.rsplit_once('@') {
+        // ignore `@` signs that are part of the package name
+        Some((package, revision)) if !revision.contains('/') && !revision.contains(':') => {
+            (package.to_owned(), PackageRevision::parse(revision))
+        }
+        _ => (specifier.to_owned(), None),
     }
 }
 
+/// Test if a package is a directory inside the project, given by a path
+/// relative to the project root, rather than a repository.
+pub fn is_local_package<S: AsRef<str>>(url: S) -> bool {
+    let url = url.as_ref();
+    url.starts_with("./") || url.starts_with("../") || url.starts_with('/')
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::util::{is_program_in_path, package_into_git_url};
-
-    #[test]
-    fn test_url_regex() {
-        let url = "github.com/rust-lang/rust";
-        assert!(super::URL_REGEX.is_match(url));
-        let url = "rust-lang/rust";
-        assert!(!super::URL_REGEX.is_match(url));
-    }
+    use crate::util::is_program_in_path;
 
     #[test]
     fn test_is_program_in_path() {
@@ -79,50 +93,37 @@ mod tests {
     }
 
     #[test]
-    fn test_package_into_git_url() {
+    fn test_split_package_specifier() {
+        use super::{split_package_specifier, PackageRevision};
+
+        assert_eq!(split_package_specifier("owner/repo"), ("owner/repo".to_owned(), None));
         assert_eq!(
-            package_into_git_url("skyezerfox/hooks").unwrap(),
-            "github.com/skyezerfox/hooks"
+            split_package_specifier("owner/repo@v1.4.0"),
+            ("owner/repo".to_owned(), Some(PackageRevision::Version("v1.4.0".to_owned())))
         );
         assert_eq!(
-            package_into_git_url("github.com/skyezerfox/hooks/some/sub/directories").unwrap(),
-            "github.com/skyezerfox/hooks"
+            split_package_specifier("owner/repo/sub@^1.2"),
+            ("owner/repo/sub".to_owned(), Some(PackageRevision::Version("^1.2".to_owned())))
         );
         assert_eq!(
-            package_into_git_url("mygitserver.com/skyezerfox/hooks").unwrap(),
-            "mygitserver.com/skyezerfox/hooks"
-        )
-    }
-
-    #[test]
-    fn test_resolve_package_url() {
-        assert_eq!(
-            super::resolve_package_url("skyezerfox/hooks").unwrap(),
-            "github.com/skyezerfox/hooks"
+            split_package_specifier("owner/repo#feature/x"),
+            ("owner/repo".to_owned(), Some(PackageRevision::Branch("feature/x".to_owned())))
         );
         assert_eq!(
-            super::resolve_package_url("github.com/skyezerfox/hooks/project").unwrap(),
-            "github.com/skyezerfox/hooks/project"
+            split_package_specifier("owner/repo@1a2b3c4d"),
+            ("owner/repo".to_owned(), Some(PackageRevision::Commit("1a2b3c4d".to_owned())))
         );
         assert_eq!(
-            super::resolve_package_url("mygitserver.com/skyezerfox/hooks/project").unwrap(),
-            "mygitserver.com/skyezerfox/hooks/project"
-        )
-    }
-
-    #[test]
-    fn test_extract_package_name() {
-        assert_eq!(
-            super::extract_package_path("skyezerfox/eslint-hook").unwrap(),
-            "."
+            split_package_specifier("git@git.acme.dev:team/hooks.git@v2"),
+            (
+                "git@git.acme.dev:team/hooks.git".to_owned(),
+                Some(PackageRevision::Version("v2".to_owned()))
+            )
         );
         assert_eq!(
-            super::extract_package_path("github.com/skyezerfox/hooks/project").unwrap(),
-            "project"
+            split_package_specifier("ssh://git@git.acme.dev/team/hooks"),
+            ("ssh://git@git.acme.dev/team/hooks".to_owned(), None)
         );
-        assert_eq!(
-            super::extract_package_path("mygitserver.com/skyezerfox/hooks/deep/project").unwrap(),
-            "deep/project"
-        )
+        assert_eq!(PackageRevision::parse("*"), None);
     }
 }